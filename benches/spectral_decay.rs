@@ -0,0 +1,114 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use spectral_decay::{
+    fft_sizes,
+    ring_buffer::RingBuffer,
+    SpectralDecay,
+    SpectralDecayParameters
+};
+
+const SAMPLE_RATE: f32 = 44100.;
+// a typical host block
+const BLOCK: usize = 512;
+
+// a few seconds of chords and noise, the same every run
+fn fixture(len: usize) -> Vec<f32> {
+    let mut seed = 0x2545_f491u32;
+    (0..len).map(|i| {
+        let t = i as f32 / SAMPLE_RATE;
+        let tone: f32 = [110., 220. * 1.26, 440. * 1.5, 1760.].iter()
+            .map(|f| (2. * std::f32::consts::PI * f * t).sin())
+            .sum();
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let noise = seed as f32 / u32::MAX as f32 - 0.5;
+        0.2 * tone + 0.1 * noise
+    }).collect()
+}
+
+// middle of the grain_select range that picks index
+fn select(index: usize, num_sizes: usize) -> f32 {
+    (index as f32 + 0.5) / num_sizes as f32
+}
+
+fn params() -> SpectralDecayParameters {
+    SpectralDecayParameters {
+        fuzz: 0.3,
+        loss: 0.5,
+        glitch_freq: 0.1,
+        glitch_gain: 10.,
+        ..Default::default()
+    }
+}
+
+// one block at a time, the grain size of every octave
+fn process(c: &mut Criterion) {
+    let sizes = fft_sizes::default_sizes(SAMPLE_RATE);
+    let input = fixture(SAMPLE_RATE as usize);
+    let mut output = vec![0.; BLOCK];
+
+    let mut group = c.benchmark_group("process");
+    group.throughput(Throughput::Elements(BLOCK as u64));
+    for index in (0..sizes.len()).step_by(9) {
+        let mut sd = SpectralDecay::new(&sizes);
+        sd.set_sample_rate(SAMPLE_RATE);
+        sd.set_params(SpectralDecayParameters { grain_select: select(index, sizes.len()), ..params() });
+
+        let mut blocks = input.chunks_exact(BLOCK).cycle();
+        group.bench_function(BenchmarkId::from_parameter(sizes[index]), |b| b.iter(|| {
+            sd.process(black_box(blocks.next().unwrap()), &mut output);
+        }));
+    }
+    group.finish();
+}
+
+// switching back and forth between a neighbouring grain size, which interpolates, and a distant one, which resets
+fn grain_switch(c: &mut Criterion) {
+    let sizes = fft_sizes::default_sizes(SAMPLE_RATE);
+    let input = fixture(BLOCK);
+    let mut output = vec![0.; BLOCK];
+
+    let mut group = c.benchmark_group("set_params");
+    for &(name, to) in &[("neighbour", 1), ("distant", sizes.len() - 1)] {
+        let mut sd = SpectralDecay::new(&sizes);
+        sd.set_sample_rate(SAMPLE_RATE);
+        let selects = [select(0, sizes.len()), select(to, sizes.len())];
+        let mut i = 0;
+
+        group.bench_function(name, |b| b.iter(|| {
+            i ^= 1;
+            sd.set_params(SpectralDecayParameters { grain_select: selects[i], ..params() });
+            sd.process(&input, &mut output);
+        }));
+    }
+    group.finish();
+}
+
+fn ring_buffer(c: &mut Criterion) {
+    let size = 8192;
+    let src = fixture(BLOCK);
+    let mut dst = vec![0.; BLOCK];
+
+    let mut group = c.benchmark_group("ring_buffer");
+    group.throughput(Throughput::Elements(BLOCK as u64));
+
+    let mut buffer = RingBuffer::new(size, false);
+    group.bench_function("copy_append_remove", |b| b.iter(|| {
+        buffer.copy_append(black_box(&src));
+        buffer.copy_remove(&mut dst);
+    }));
+
+    let mut buffer = RingBuffer::new(size, true);
+    group.bench_function("copy_replace", |b| b.iter(|| {
+        buffer.copy_replace(Some(black_box(&src)), Some(&mut dst));
+    }));
+
+    let mut buffer = RingBuffer::new(size, true);
+    group.bench_function("extend_overwrite", |b| b.iter(|| {
+        buffer.extend_overwrite(black_box(&src));
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, process, grain_switch, ring_buffer);
+criterion_main!(benches);
@@ -1,82 +1,117 @@
 use spectral_decay::{
+    fft_sizes,
     SpectralDecay,
     SpectralDecayParameters
 };
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::error::Error;
 
-const AMEN: &[u8] = include_bytes!("amen.raw");
+// (end in seconds, (fuzz, loss, glitch freq, glitch gain)), grain size sweeps up over the whole run
+const AUTOMATION: [(f32, (f32, f32, f32, f32)); 14] = [
+    (0.5, (0., 0., 0., 1.)),
+    (1.0, (0., 0., 0., 1.)),
+    (1.5, (0.7, 0., 0., 1.)),
+    (2.0, (0.7, 0., 0., 1.)),
+    (2.5, (1., 0., 0., 1.)),
+    (3.0, (1., 0., 0., 1.)),
+    (3.5, (1., 0.1, 0., 1.)),
+    (4.0, (1., 0.1, 0., 1.)),
+    (4.5, (1., 0.5, 0., 1.)),
+    (5.0, (1., 0.5, 0., 1.)),
+    (5.5, (1., 1., 0.1, 100.)),
+    (6.0, (1., 1., 0.1, 100.)),
+    (6.5, (1., 1., 1., 50.)),
+    (7.0, (1., 1., 1., 50.)),
+];
 
-fn main() {
-    use std::io::{self, Write};
+fn read_wav(path: &str) -> Result<(WavSpec, Vec<f32>), Box<dyn Error>> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
 
-    let wav = unsafe {
-        // I hope you're on a little endian machine
-        std::mem::transmute::<&[u8], &[i16]>(AMEN)
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        SampleFormat::Int => {
+            let scale = (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|x| x.map(|x| x as f32 / scale)).collect::<Result<Vec<_>, _>>()?
+        }
     };
 
-    let input: Vec<_> = wav.iter().map(|x| *x as f32 / (i16::MAX - 1) as f32).collect();
-    let mut output = vec![0f32; AMEN.len()];
-
-    let mut sp = SpectralDecay::new(&[
-        64,
-        64 * 3/2,
-        128,
-        128 * 3/2,
-        256,
-        256 * 3/2,
-        512,
-        512 * 3/2,
-        1024,
-        1024 * 3/2,
-        2048,
-        2048 * 3/2,
-        4096,
-        4096 * 3/2,
-        8192,
-        ]);
-
-    let automation = [
-        // (1.0, (0., 0., 0., 0., 0.)),
-        // (2.0, (1., 0., 0., 0., 0.)),
-        // (3.0, (0., 0., 0., 0., 0.)),
-        // (4.0, (1., 0., 0., 0., 0.)),
-
-        (0.5, (0., 0., 0., 0., 1.)),
-        (1.0, (1., 0., 0., 0., 1.)),
-        (1.5, (0., 0.7, 0., 0., 1.)),
-        (2.0, (1., 0.7, 0., 0., 1.)),
-        (2.5, (0., 1., 0., 0., 1.)),
-        (3.0, (1., 1., 0., 0., 1.)),
-        (3.5, (0., 1., 0.1, 0., 1.)),
-        (4.0, (1., 1., 0.1, 0., 1.)),
-        (4.5, (0., 1., 0.5, 0., 1.)),
-        (5.0, (1., 1., 0.5, 0., 1.)),
-        (5.5, (0., 1., 1., 0.1, 100.)),
-        (6.0, (1., 1., 1., 0.1, 100.)),
-        (6.5, (0., 1., 1., 1., 50.)),
-        (7.0, (1., 1., 1., 1., 50.)),
-    ];
+    Ok((spec, samples))
+}
+
+fn write_wav(path: &str, spec: WavSpec, samples: &[f32]) -> Result<(), Box<dyn Error>> {
+    let mut writer = WavWriter::create(path, spec)?;
+
+    match spec.sample_format {
+        SampleFormat::Float => for &x in samples {
+            writer.write_sample(x)?;
+        },
+        SampleFormat::Int => {
+            let scale = ((1u32 << (spec.bits_per_sample - 1)) - 1) as f32;
+            for &x in samples {
+                writer.write_sample((x.clamp(-1., 1.) * scale) as i32)?;
+            }
+        }
+    }
+
+    writer.finalize()?;
+    Ok(())
+}
+
+// run the automation over one channel, holding the last step until the end of the file
+fn render(sd: &mut SpectralDecay, input: &[f32], sample_rate: f32) -> Vec<f32> {
+    let mut output = vec![0.; input.len()];
+    let last = AUTOMATION.len() - 1;
+
     let mut start = 0;
-    for &(end, (_gs, p, l, gf, gg)) in &automation {
-        let gs = end / 7.0;
-        let end = (end * 44100.) as usize;
-        sp.set_params(SpectralDecayParameters {
+    for (i, &(end, (p, l, gf, gg))) in AUTOMATION.iter().enumerate() {
+        let gs = end / AUTOMATION[last].0;
+        let end = if i == last { input.len() } else { ((end * sample_rate) as usize).min(input.len()) };
+        sd.set_params(SpectralDecayParameters {
             grain_select: gs,
             fuzz: p,
             loss: l,
             glitch_freq: gf,
             glitch_gain: gg,
+            ..Default::default()
         });
-        sp.process(&input[start..end], &mut output[start..end]);
+        sd.process(&input[start..end], &mut output[start..end]);
         start = end;
     }
 
-    let output_int: Vec<_> = output.iter().map(|&x| (x.clamp(-1., 1.) * (i16::MAX - 1) as f32) as i16).collect();
+    output
+}
+
+// the bundled break, when no input is given
+const AMEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/amen.wav");
+
+// cargo run --example demo -- [input.wav [output.wav]], writing demo.wav unless told otherwise
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    if args.len() > 2 {
+        return Err("usage: demo [input.wav [output.wav]]".into());
+    }
+    let input = args.get(0).map_or(AMEN, |x| x.as_str());
+    let output_path = args.get(1).map_or("demo.wav", |x| x.as_str());
+
+    let (spec, samples) = read_wav(input)?;
+    let channels = spec.channels as usize;
+    let sample_rate = spec.sample_rate as f32;
+    let grain_sizes = &fft_sizes::default_sizes(sample_rate);
 
-    let bytes = unsafe {
-        std::mem::transmute::<&[i16], &[u8]>(&output_int)
-    };
+    let mut output = vec![0.; samples.len()];
+
+    for c in 0..channels {
+        let mut sd = SpectralDecay::try_new(grain_sizes)?;
+        sd.set_sample_rate(sample_rate);
+
+        let input: Vec<_> = samples.iter().skip(c).step_by(channels).copied().collect();
+        let rendered = render(&mut sd, &input, sample_rate);
+
+        for (y, x) in output.iter_mut().skip(c).step_by(channels).zip(rendered) {
+            *y = x;
+        }
+    }
 
-    // play this using sox or aplayer, e.g.
-    // cargo run --example demo | sox -traw -r44100 -b16 -e signed-integer - -tcoreaudio
-    io::stdout().write_all(bytes).unwrap();
+    write_wav(output_path, spec, &output)
 }
@@ -0,0 +1,119 @@
+use spectral_decay::{
+    fft_sizes,
+    spsc,
+    SpectralDecay,
+    SpectralDecayParameters
+};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::error::Error;
+use std::io::{self, BufRead};
+
+// cargo run --release --example live
+// runs the default input through the effect to the default output, in mono
+// type keys and press enter to change parameters, lowercase turns down and uppercase turns up
+const KEYS: &str = "\
+f/F  fuzz
+l/L  loss
+g/G  glitch frequency
+v/V  glitch gain
+s/S  grain size
+m/M  mix
+p/P  pitch
+z    toggle freeze
+q    quit";
+
+// fraction of a 0..1 parameter's range each key press moves it by
+const STEP: f32 = 0.05;
+
+// room for this many samples between the input and output callbacks, and per process() call
+const BUFFER: usize = 8192;
+
+// returns false on quit
+fn apply_key(params: &mut SpectralDecayParameters, key: char) -> bool {
+    let step = |x: &mut f32, up: bool, lo: f32, hi: f32, by: f32| {
+        *x = (*x + if up { by } else { -by }).clamp(lo, hi);
+    };
+    let up = key.is_uppercase();
+    match key.to_ascii_lowercase() {
+        'f' => step(&mut params.fuzz, up, 0., 1., STEP),
+        'l' => step(&mut params.loss, up, 0., 1., STEP),
+        'g' => step(&mut params.glitch_freq, up, 0., 1., STEP),
+        'v' => step(&mut params.glitch_gain, up, 1., 100., 100. * STEP),
+        's' => step(&mut params.grain_select, up, 0., 1., STEP),
+        'm' => step(&mut params.mix, up, 0., 1., STEP),
+        'p' => step(&mut params.pitch, up, -24., 24., 1.),
+        'z' => params.freeze = !params.freeze,
+        'q' => return false,
+        _ => (),
+    }
+    true
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let host = cpal::default_host();
+    let input_device = host.default_input_device().ok_or("no input device")?;
+    let output_device = host.default_output_device().ok_or("no output device")?;
+
+    // both sides run at the input's rate
+    let config: cpal::StreamConfig = input_device.default_input_config()?.into();
+    let sample_rate = config.sample_rate.0 as f32;
+    let in_channels = config.channels as usize;
+    let out_config = cpal::StreamConfig { channels: output_device.default_output_config()?.channels(), ..config.clone() };
+    let out_channels = out_config.channels as usize;
+
+    let mut params = SpectralDecayParameters::default();
+    let mut sd = SpectralDecay::try_new(&fft_sizes::default_sizes(sample_rate))?;
+    sd.set_sample_rate(sample_rate);
+
+    let (mut audio_in, mut audio_out) = spsc::channel::<f32>(BUFFER);
+    let (mut params_in, mut params_out) = spsc::channel::<SpectralDecayParameters>(16);
+
+    let input_stream = input_device.build_input_stream(&config, move |data: &[f32], _: &cpal::InputCallbackInfo| {
+        let mut mono = [0.; BUFFER];
+        let frames = data.len() / in_channels;
+        for (y, frame) in mono.iter_mut().zip(data.chunks(in_channels)) {
+            *y = frame.iter().sum::<f32>() / in_channels as f32;
+        }
+        // drops whatever the output side hasn't made room for
+        audio_in.push_slice(&mono[..frames.min(BUFFER)]);
+    }, |e| eprintln!("input error: {}", e))?;
+
+    let mut input = vec![0.; BUFFER];
+    let mut output = vec![0.; BUFFER];
+    let output_stream = output_device.build_output_stream(&out_config, move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+        let mut latest = [SpectralDecayParameters::default(); 16];
+        let n = params_out.pop_slice(&mut latest);
+        if n > 0 {
+            sd.set_params(latest[n - 1]);
+        }
+
+        let frames = (data.len() / out_channels).min(BUFFER);
+        // silence when the input falls behind
+        let read = audio_out.pop_slice(&mut input[..frames]);
+        input[read..frames].iter_mut().for_each(|x| *x = 0.);
+
+        sd.process(&input[..frames], &mut output[..frames]);
+        for (frame, &x) in data.chunks_mut(out_channels).zip(&output[..frames]) {
+            frame.iter_mut().for_each(|y| *y = x);
+        }
+    }, |e| eprintln!("output error: {}", e))?;
+
+    input_stream.play()?;
+    output_stream.play()?;
+
+    println!("{}", KEYS);
+    for line in io::stdin().lock().lines() {
+        for key in line?.chars() {
+            if !apply_key(&mut params, key) {
+                return Ok(());
+            }
+        }
+        params_in.push_slice(&[params]);
+        println!(
+            "fuzz {:.2}  loss {:.2}  glitch {:.2} x{:.0}  grain {:.2}  mix {:.2}  pitch {:+}  freeze {}",
+            params.fuzz, params.loss, params.glitch_freq, params.glitch_gain,
+            params.grain_select, params.mix, params.pitch, params.freeze
+        );
+    }
+    Ok(())
+}
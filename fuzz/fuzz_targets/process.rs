@@ -0,0 +1,105 @@
+#![no_main]
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use spectral_decay::{
+    Component,
+    LfoParameters,
+    LfoShape,
+    LfoTarget,
+    Overlap,
+    PhaseMode,
+    SpectralDecay,
+    SpectralDecayParameters
+};
+
+// cargo fuzz run process
+// random interleavings of blocks and parameter changes, looking for panics in the buffer splitting and grain switching
+
+const GRAIN_SIZES: &[usize] = &[32, 48, 64, 96, 128, 256, 512];
+// longer blocks don't reach anything new, just slow the fuzzer down
+const MAX_BLOCK: usize = 2048;
+
+// raw knob positions, mapped onto each parameter's range so extremes come up often
+#[derive(Arbitrary, Debug)]
+struct Knobs {
+    values: [u8; 24],
+    switches: u8,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Process { len: u16, sample: i8 },
+    Sidechain { len: u16, sample: i8 },
+    SetParams(Knobs),
+    TriggerGlitch,
+    Reset,
+}
+
+impl Knobs {
+    fn params(&self) -> SpectralDecayParameters {
+        let v = |i: usize, lo: f32, hi: f32| lo + (hi - lo) * self.values[i] as f32 / u8::MAX as f32;
+        let switch = |bit: u8| self.switches & (1 << bit) != 0;
+        let pick = |i: usize, len: usize| self.values[i] as usize % len;
+
+        SpectralDecayParameters {
+            grain_select: v(0, 0., 1.),
+            fuzz: v(1, 0., 1.),
+            loss: v(2, 0., 1.),
+            glitch_freq: v(3, 0., 1.),
+            glitch_gain: v(4, 1., 100.),
+            delay_select: v(5, 0., 1.),
+            freeze: switch(0),
+            mix: v(6, 0., 1.),
+            pitch: v(7, -24., 24.),
+            overlap: [Overlap::Half, Overlap::ThreeQuarters, Overlap::SevenEighths][pick(8, 3)],
+            gate_attack: v(9, 0., 32.),
+            gate_release: v(10, 0., 32.),
+            spectral_delay: v(11, 0., 64.),
+            spectral_delay_feedback: v(12, 0., 1.),
+            blur: v(13, 0., 1.),
+            decay: v(14, 0., 1.),
+            duck: v(15, 0., 1.),
+            glitch_synced: switch(1),
+            phase_mode: [PhaseMode::Normal, PhaseMode::Robot, PhaseMode::Whisper][pick(16, 3)],
+            component: [Component::Both, Component::Harmonic, Component::Percussive][pick(17, 3)],
+            compress_threshold: v(18, -60., 0.),
+            compress_ratio: v(19, 1., 20.),
+            tilt: v(20, -12., 12.),
+            scramble: v(21, 0., 1.),
+            scramble_range: v(22, 1., 64.),
+            crush: v(23, 0., 64.),
+            crush_db: switch(2),
+            limiter: switch(3),
+            preserve_transients: if switch(4) { 1. } else { 0. },
+            adaptive_loss: if switch(5) { 1. } else { 0. },
+            lfo: [
+                LfoParameters { rate: 20., depth: if switch(6) { 1. } else { 0. }, shape: LfoShape::SampleAndHold, target: LfoTarget::Pitch },
+                LfoParameters { rate: 5., depth: if switch(7) { 1. } else { 0. }, shape: LfoShape::Square, target: LfoTarget::Loss },
+            ],
+            ..Default::default()
+        }
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut sd = SpectralDecay::new(GRAIN_SIZES);
+    let mut output = vec![0.; MAX_BLOCK];
+
+    for op in ops {
+        match op {
+            Op::Process { len, sample } => {
+                let len = len as usize % MAX_BLOCK;
+                let input = vec![sample as f32 / 128.; len];
+                sd.process(&input, &mut output[..len]);
+            }
+            Op::Sidechain { len, sample } => {
+                let len = len as usize % MAX_BLOCK;
+                let input = vec![sample as f32 / 128.; len];
+                sd.process_sidechain(&input, &input, &mut output[..len]);
+            }
+            Op::SetParams(knobs) => sd.set_params(knobs.params()),
+            Op::TriggerGlitch => sd.trigger_glitch(),
+            Op::Reset => sd.reset(),
+        }
+    }
+});
@@ -0,0 +1,194 @@
+use crate::spectral_decay::SpectralDecayParameters;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// timestamped parameter values for offline rendering, stored as json, one lane per parameter:
+// { "loss": [{ "time": 0, "value": 0 }, { "time": 4, "value": 1 }], "freeze": [{ "time": 2, "value": 1 }] }
+// times are in seconds, before the first point and after the last a lane holds that point's value
+// switches (freeze, accumulate, glitch_synced, crush_db, loss_invert, loss_db, limiter, dc_block, auto_gain and bypass) are on above 0.5 and always step
+
+// how the value gets from a point to the next one
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    Linear,
+    // hold until the next point
+    Step,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve::Linear
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub time: f32,
+    pub value: f32,
+    #[serde(default)]
+    pub curve: Curve,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Automation {
+    lanes: BTreeMap<String, Vec<Point>>,
+}
+
+// write to the parameter a lane is named after, false if there's no such parameter
+fn set(params: &mut SpectralDecayParameters, name: &str, value: f32) -> bool {
+    let switch = value > 0.5;
+    match name {
+        "grain_select" => params.grain_select = value,
+        "fuzz" => params.fuzz = value,
+        "fuzz_slope" => params.fuzz_slope = value,
+        "loss" => params.loss = value,
+        "glitch_freq" => params.glitch_freq = value,
+        "glitch_gain" => params.glitch_gain = value,
+        "glitch_tilt" => params.glitch_tilt = value,
+        "glitch_hold" => params.glitch_hold = value,
+        "glitch_decay" => params.glitch_decay = value,
+        "delay_select" => params.delay_select = value,
+        "synthesis_select" => params.synthesis_select = value,
+        "freeze" => params.freeze = switch,
+        "melt" => params.melt = value,
+        "accumulate" => params.accumulate = switch,
+        "partials" => params.partials = value,
+        "partial_detune" => params.partial_detune = value,
+        "partial_decay" => params.partial_decay = value,
+        "mix" => params.mix = value,
+        "pitch" => params.pitch = value,
+        "spread" => params.spread = value,
+        "formant" => params.formant = value,
+        "lifter" => params.lifter = value,
+        "gate_attack" => params.gate_attack = value,
+        "gate_release" => params.gate_release = value,
+        "spectral_delay" => params.spectral_delay = value,
+        "spectral_delay_feedback" => params.spectral_delay_feedback = value,
+        "blur" => params.blur = value,
+        "decay" => params.decay = value,
+        "duck" => params.duck = value,
+        "morph" => params.morph = value,
+        "vocode" => params.vocode = value,
+        "print" => params.print = value,
+        "denoise" => params.denoise = value,
+        "denoise_floor" => params.denoise_floor = value,
+        "glitch_synced" => params.glitch_synced = switch,
+        "compress_threshold" => params.compress_threshold = value,
+        "compress_ratio" => params.compress_ratio = value,
+        "compress_attack" => params.compress_attack = value,
+        "compress_release" => params.compress_release = value,
+        "tilt" => params.tilt = value,
+        "tilt_center" => params.tilt_center = value,
+        "scramble" => params.scramble = value,
+        "scramble_range" => params.scramble_range = value,
+        "crush" => params.crush = value,
+        "crush_db" => params.crush_db = switch,
+        "low_cut" => params.low_cut = value,
+        "high_cut" => params.high_cut = value,
+        "adaptive_loss" => params.adaptive_loss = value,
+        "loss_invert" => params.loss_invert = switch,
+        "loss_db" => params.loss_db = switch,
+        "preserve_transients" => params.preserve_transients = value,
+        "limiter" => params.limiter = switch,
+        "ceiling" => params.ceiling = value,
+        "dc_block" => params.dc_block = switch,
+        "auto_gain" => params.auto_gain = switch,
+        "stretch" => params.stretch = value,
+        "jitter" => params.jitter = value,
+        "reverse" => params.reverse = value,
+        "bypass" => params.bypass = switch,
+        _ => return false,
+    }
+    true
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Automation {
+    pub fn parse(json: &str) -> io::Result<Self> {
+        let automation: Self = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+
+        for (name, points) in &automation.lanes {
+            if !set(&mut SpectralDecayParameters::default(), name, 0.) {
+                return Err(invalid(format!("unknown parameter {}", name)));
+            }
+            if points.is_empty() {
+                return Err(invalid(format!("no points for {}", name)));
+            }
+            if !points.windows(2).all(|p| p[0].time <= p[1].time) {
+                return Err(invalid(format!("points for {} are not in time order", name)));
+            }
+        }
+        Ok(automation)
+    }
+
+    // time of the last point of any lane
+    pub fn end(&self) -> f32 {
+        self.lanes.values().map(|points| points.last().unwrap().time).fold(0., f32::max)
+    }
+
+    // overwrite every automated parameter with its value at time, in seconds
+    pub fn apply(&self, params: &mut SpectralDecayParameters, time: f32) {
+        for (name, points) in &self.lanes {
+            // index of the first point after time
+            let next = points.iter().position(|p| p.time > time).unwrap_or(points.len());
+            let value = match next {
+                0 => points[0].value,
+                n if n == points.len() => points[n - 1].value,
+                n => {
+                    let (a, b) = (points[n - 1], points[n]);
+                    match a.curve {
+                        Curve::Step => a.value,
+                        Curve::Linear => a.value + (b.value - a.value) * (time - a.time) / (b.time - a.time),
+                    }
+                }
+            };
+            set(params, name, value);
+        }
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<Automation> {
+    Automation::parse(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolation() {
+        let automation = Automation::parse(r#"{
+            "loss": [{ "time": 1, "value": 0 }, { "time": 3, "value": 1, "curve": "step" }, { "time": 4, "value": 0 }],
+            "freeze": [{ "time": 2, "value": 1 }]
+        }"#).unwrap();
+        assert_eq!(automation.end(), 4.);
+
+        let at = |time: f32| {
+            let mut params = SpectralDecayParameters::default();
+            automation.apply(&mut params, time);
+            params
+        };
+
+        assert_eq!(at(0.).loss, 0.);
+        assert_eq!(at(2.).loss, 0.5);
+        assert_eq!(at(3.5).loss, 1.);
+        assert_eq!(at(5.).loss, 0.);
+        assert!(at(0.).freeze);
+        assert_eq!(at(2.).fuzz, 0.);
+    }
+
+    #[test]
+    fn invalid_lanes() {
+        assert!(Automation::parse(r#"{ "volume": [{ "time": 0, "value": 1 }] }"#).is_err());
+        assert!(Automation::parse(r#"{ "loss": [] }"#).is_err());
+        assert!(Automation::parse(r#"{ "loss": [{ "time": 1, "value": 1 }, { "time": 0, "value": 0 }] }"#).is_err());
+    }
+}
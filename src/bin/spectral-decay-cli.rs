@@ -0,0 +1,356 @@
+use spectral_decay::{
+    automation::{self, Automation},
+    fft_sizes,
+    Complex,
+    GrainPlans,
+    GrainTable,
+    Overlap,
+    SpectralDecay,
+    SpectralDecayParameters,
+    WindowKind
+};
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use rayon::prelude::*;
+use realfft::RealFftPlanner;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const USAGE: &str = "\
+usage: spectral-decay-cli [options] <input.wav> <output.wav>
+       spectral-decay-cli [options] --batch <suffix> <input.wav or directory>...
+
+options:
+    --grain <0..1>          grain size
+    --delay <0..1>          delay compensation
+    --fuzz <0..1>           phase randomization
+    --loss <0..1>           drop bins quieter than this fraction of the loudest bin
+    --glitch-freq <0..1>    probability of glitching a bin
+    --glitch-gain <1..100>  max gain of a glitched bin
+    --mix <0..1>            dry/wet
+    --pitch <semitones>     transposition
+    --stretch <ratio>       output length over input length, keeping the pitch
+    --overlap <2|4|8>       grains covering each sample
+    --window <kind>         hann, blackman-harris, tukey or flat-top
+    --zero-pad              FFT every grain padded to twice its length, smoother but twice the work
+    --freeze                latch the first grain
+    --automation <path>     json lanes of timestamped parameter values, applied every hop
+    --spectrogram <path>    also render a png of the input's spectrum above the output's
+    --batch <suffix>        process every input, and every wav file in input directories, in parallel
+                            writing each next to its input, with the suffix added to the file name
+";
+
+// analysis size of the spectrogram, and the dB range its brightness covers
+const SPECTROGRAM_SIZE: usize = 1024;
+const SPECTROGRAM_HOP: usize = 256;
+const SPECTROGRAM_FLOOR_DB: f32 = -90.;
+
+struct Args {
+    params: SpectralDecayParameters,
+    window: WindowKind,
+    zero_pad: bool,
+    // (input, output) pairs
+    files: Vec<(PathBuf, PathBuf)>,
+    automation: Option<Automation>,
+    spectrogram: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut params = SpectralDecayParameters::default();
+    let mut window = WindowKind::Hann;
+    let mut zero_pad = false;
+    let mut automation = None;
+    let mut spectrogram = None;
+    let mut batch = None;
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            paths.push(arg);
+            continue;
+        }
+        if arg == "--freeze" {
+            params.freeze = true;
+            continue;
+        }
+        if arg == "--zero-pad" {
+            zero_pad = true;
+            continue;
+        }
+        if arg == "--window" {
+            window = match args.next().as_deref() {
+                Some("hann") => WindowKind::Hann,
+                Some("blackman-harris") => WindowKind::BlackmanHarris,
+                Some("tukey") => WindowKind::Tukey,
+                Some("flat-top") => WindowKind::FlatTop,
+                Some(kind) => return Err(format!("unknown window {}", kind)),
+                None => return Err(format!("missing value for {}", arg))
+            };
+            continue;
+        }
+        if arg == "--automation" {
+            let path = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+            automation = Some(automation::load(&path).map_err(|e| format!("invalid automation {}: {}", path, e))?);
+            continue;
+        }
+        if arg == "--batch" {
+            batch = Some(args.next().ok_or_else(|| format!("missing value for {}", arg))?);
+            continue;
+        }
+        if arg == "--spectrogram" {
+            spectrogram = Some(args.next().ok_or_else(|| format!("missing value for {}", arg))?);
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+        let value: f32 = value.parse().map_err(|_| format!("invalid value for {}: {}", arg, value))?;
+
+        match arg.as_str() {
+            "--grain" => params.grain_select = value,
+            "--delay" => params.delay_select = value,
+            "--fuzz" => params.fuzz = value,
+            "--loss" => params.loss = value,
+            "--glitch-freq" => params.glitch_freq = value,
+            "--glitch-gain" => params.glitch_gain = value,
+            "--mix" => params.mix = value,
+            "--pitch" => params.pitch = value,
+            "--stretch" if value > 0. => params.stretch = value,
+            "--overlap" => params.overlap = match value as usize {
+                2 => Overlap::Half,
+                4 => Overlap::ThreeQuarters,
+                8 => Overlap::SevenEighths,
+                _ => return Err(format!("invalid value for {}: {}", arg, value))
+            },
+            _ => return Err(format!("unknown option {}", arg))
+        }
+    }
+
+    // automation is timed by the input, which no longer lines up with the output
+    if params.stretch != 1. && automation.is_some() {
+        return Err("--stretch doesn't work with --automation".into());
+    }
+
+    let files = match batch {
+        Some(suffix) => {
+            if spectrogram.is_some() {
+                return Err("--spectrogram only works on a single input".into());
+            }
+            // an empty suffix would overwrite the inputs
+            if suffix.is_empty() {
+                return Err("empty value for --batch".into());
+            }
+            let files = batch_files(&paths, &suffix).map_err(|e| e.to_string())?;
+            if files.is_empty() {
+                return Err("no wav files to process".into());
+            }
+            files
+        }
+        None => {
+            if paths.len() != 2 {
+                return Err("expected an input and an output path".into());
+            }
+            let output = paths.pop().unwrap();
+            let input = paths.pop().unwrap();
+            vec![(input.into(), output.into())]
+        }
+    };
+
+    Ok(Args { params, window, zero_pad, files, automation, spectrogram })
+}
+
+// input.wav becomes input<suffix>.wav, directories are searched one level deep
+fn batch_files(paths: &[String], suffix: &str) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let is_wav = |path: &Path| path.extension().map_or(false, |ext| ext.eq_ignore_ascii_case("wav"));
+
+    let mut inputs = Vec::new();
+    for path in paths.iter().map(Path::new) {
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.retain(|path| path.is_file() && is_wav(path));
+            entries.sort();
+            inputs.extend(entries);
+        } else {
+            inputs.push(path.to_path_buf());
+        }
+    }
+
+    // don't pick up the outputs of an earlier run
+    inputs.retain(|path| !path.file_stem().map_or(false, |stem| stem.to_string_lossy().ends_with(suffix)));
+
+    Ok(inputs.into_iter().map(|input| {
+        let mut name = input.file_stem().unwrap_or_default().to_os_string();
+        name.push(suffix);
+        name.push(".wav");
+        let output = input.with_file_name(name);
+        (input, output)
+    }).collect())
+}
+
+fn read_wav(path: &Path) -> Result<(WavSpec, Vec<f32>), Box<dyn Error>> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        SampleFormat::Int => {
+            let scale = (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|x| x.map(|x| x as f32 / scale)).collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    Ok((spec, samples))
+}
+
+fn write_wav(path: &Path, spec: WavSpec, samples: &[f32]) -> Result<(), Box<dyn Error>> {
+    let mut writer = WavWriter::create(path, spec)?;
+
+    match spec.sample_format {
+        SampleFormat::Float => for &x in samples {
+            writer.write_sample(x)?;
+        },
+        SampleFormat::Int => {
+            let scale = ((1u32 << (spec.bits_per_sample - 1)) - 1) as f32;
+            for &x in samples {
+                writer.write_sample((x.clamp(-1., 1.) * scale) as i32)?;
+            }
+        }
+    }
+
+    writer.finalize()?;
+    Ok(())
+}
+
+// magnitudes in dB of every hop of a mono signal, low frequencies first, DC left out
+fn spectrogram(samples: &[f32]) -> Result<Vec<Vec<f32>>, Box<dyn Error>> {
+    let table = GrainTable::new(&[SPECTROGRAM_SIZE], WindowKind::Hann)?;
+    let window = table.window(0);
+    let fft = RealFftPlanner::new().plan_fft_forward(SPECTROGRAM_SIZE);
+    let mut scratch = fft.make_scratch_vec();
+    let mut time_buf = vec![0.; SPECTROGRAM_SIZE];
+    let mut freq_buf = vec![Complex::new(0., 0.); SPECTROGRAM_SIZE / 2 + 1];
+
+    // a full scale sine peaks at 0 dB
+    let scale = 2. / (SPECTROGRAM_SIZE as f32 * table.window_kind().mean());
+
+    let mut columns = Vec::new();
+    for start in (0..samples.len()).step_by(SPECTROGRAM_HOP) {
+        for (i, (y, &w)) in time_buf.iter_mut().zip(window).enumerate() {
+            *y = samples.get(start + i).copied().unwrap_or(0.) * w;
+        }
+        fft.process_with_scratch(&mut time_buf, &mut freq_buf, &mut scratch)?;
+        columns.push(freq_buf[1..].iter().map(|x| 20. * (x.norm() * scale).log10()).collect());
+    }
+    Ok(columns)
+}
+
+fn write_spectrogram(path: &str, input: &[f32], output: &[f32]) -> Result<(), Box<dyn Error>> {
+    let halves = [spectrogram(input)?, spectrogram(output)?];
+    // stretched, the shorter half is padded out with black
+    let width = halves[0].len().max(halves[1].len());
+    let height = SPECTROGRAM_SIZE / 2;
+
+    // one row per bin, the highest at the top of each half
+    let mut pixels = Vec::with_capacity(width * height * 2);
+    for columns in halves.iter() {
+        for bin in (0..height).rev() {
+            for c in 0..width {
+                let brightness = columns.get(c).map_or(0., |column| 1. - column[bin].clamp(SPECTROGRAM_FLOOR_DB, 0.) / SPECTROGRAM_FLOOR_DB);
+                pixels.push((brightness * 255.) as u8);
+            }
+        }
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width as u32, (height * 2) as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+// with automation, parameters are updated every hop of the smallest grain
+fn render(sd: &mut SpectralDecay, input: &[f32], args: &Args, hop: usize, sample_rate: f32) -> Vec<f32> {
+    if args.params.stretch != 1. {
+        return sd.render_stretched(input, args.params.stretch);
+    }
+
+    let automation: Vec<_> = match &args.automation {
+        Some(automation) => (0..input.len()).step_by(hop).map(|start| {
+            let mut params = args.params;
+            automation.apply(&mut params, start as f32 / sample_rate);
+            (start, params)
+        }).collect(),
+        None => Vec::new(),
+    };
+
+    sd.render(input, &automation, |_| ())
+}
+
+fn run_file(args: &Args, input: &Path, output_path: &Path) -> Result<(), Box<dyn Error>> {
+    let (spec, samples) = read_wav(input)?;
+    let channels = spec.channels as usize;
+    let grain_sizes = &fft_sizes::default_sizes(spec.sample_rate as f32);
+    let hop = fft_sizes::hop(grain_sizes[0], args.params.overlap);
+
+    let frames = (samples.len() / channels) as f32 * args.params.stretch;
+    let mut output = vec![0.; frames.round() as usize * channels];
+
+    for c in 0..channels {
+        let table = Arc::new(GrainTable::new(grain_sizes, args.window)?);
+        let mut sd = SpectralDecay::with_plans(if args.zero_pad { GrainPlans::zero_padded(table)? } else { GrainPlans::new(table)? });
+        sd.set_sample_rate(spec.sample_rate as f32);
+        sd.set_params(args.params);
+
+        let input: Vec<_> = samples.iter().skip(c).step_by(channels).copied().collect();
+        let rendered = render(&mut sd, &input, args, hop, spec.sample_rate as f32);
+
+        for (y, x) in output.iter_mut().skip(c).step_by(channels).zip(rendered) {
+            *y = x;
+        }
+    }
+
+    write_wav(output_path, spec, &output)?;
+
+    if let Some(path) = &args.spectrogram {
+        let mono = |samples: &[f32]| samples.chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect::<Vec<_>>();
+        write_spectrogram(path, &mono(&samples), &mono(&output))?;
+    }
+    Ok(())
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    if let [(input, output)] = args.files.as_slice() {
+        return run_file(&args, input, output);
+    }
+
+    // keep going past files that fail, and list them all at the end
+    let errors: Vec<_> = args.files.par_iter().filter_map(|(input, output)| {
+        run_file(&args, input, output).err().map(|e| format!("{}: {}", input.display(), e))
+    }).collect();
+
+    if !errors.is_empty() {
+        return Err(errors.join("\n").into());
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(args) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
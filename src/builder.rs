@@ -0,0 +1,135 @@
+use crate::spectral_decay::{GrainPlans, Overlap, SpectralDecay, SpectralDecayError, SpectralDecayParameters};
+use crate::grain_table::GrainTable;
+use crate::window::WindowKind;
+use crate::fft_sizes;
+use std::sync::Arc;
+
+enum Grains {
+    // fft_sizes::default_sizes at the sample rate
+    Default,
+    Range { min_ms: f32, max_ms: f32 },
+    Sizes(Vec<usize>),
+}
+
+// everything about a SpectralDecay that's fixed or awkward to get right after construction,
+// picking grain sizes that are easy to FFT and valid, from durations rather than samples
+pub struct SpectralDecayBuilder {
+    grains: Grains,
+    divisions_per_octave: u64,
+    window: WindowKind,
+    zero_padded: bool,
+    sample_rate: f32,
+    seed: Option<u64>,
+    params: SpectralDecayParameters,
+}
+
+impl Default for SpectralDecayBuilder {
+    fn default() -> Self {
+        Self {
+            grains: Grains::Default,
+            divisions_per_octave: 9,
+            window: WindowKind::Hann,
+            zero_padded: false,
+            sample_rate: 44100.,
+            seed: None,
+            params: SpectralDecayParameters::default(),
+        }
+    }
+}
+
+impl SpectralDecayBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // shortest and longest grain, the sizes in between spaced by divisions_per_octave
+    pub fn grain_range_ms(mut self, min_ms: f32, max_ms: f32) -> Self {
+        self.grains = Grains::Range { min_ms, max_ms };
+        self
+    }
+
+    // exact sizes instead, which have to be multiples of 4 in ascending order
+    pub fn grain_sizes(mut self, sizes: &[usize]) -> Self {
+        self.grains = Grains::Sizes(sizes.to_vec());
+        self
+    }
+
+    pub fn divisions_per_octave(mut self, divisions: u64) -> Self {
+        self.divisions_per_octave = divisions.max(1);
+        self
+    }
+
+    pub fn window(mut self, window: WindowKind) -> Self {
+        self.window = window;
+        self
+    }
+
+    // see GrainPlans::zero_padded
+    pub fn zero_padded(mut self, zero_padded: bool) -> Self {
+        self.zero_padded = zero_padded;
+        self
+    }
+
+    // the starting overlap, it can change with the params later
+    pub fn overlap(mut self, overlap: Overlap) -> Self {
+        self.params.overlap = overlap;
+        self
+    }
+
+    // see SpectralDecay::set_seed
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: f32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn params(mut self, params: SpectralDecayParameters) -> Self {
+        let overlap = self.params.overlap;
+        self.params = params;
+        self.params.overlap = overlap;
+        self
+    }
+
+    pub fn build(self) -> Result<SpectralDecay, SpectralDecayError> {
+        let sizes = match self.grains {
+            Grains::Default => fft_sizes::default_sizes(self.sample_rate),
+            Grains::Range { min_ms, max_ms } if min_ms > max_ms => return Err(SpectralDecayError::NoGrainSizes),
+            Grains::Range { min_ms, max_ms } => fft_sizes::generate_sizes_ms(min_ms, max_ms, self.divisions_per_octave, self.sample_rate),
+            Grains::Sizes(sizes) => sizes,
+        };
+
+        let table = Arc::new(GrainTable::new(&sizes, self.window)?);
+        let grains = if self.zero_padded { GrainPlans::zero_padded(table)? } else { GrainPlans::new(table)? };
+        let mut sd = SpectralDecay::with_plans(grains);
+        sd.set_sample_rate(self.sample_rate);
+        sd.set_seed(self.seed);
+        sd.set_params(self.params);
+        Ok(sd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder() {
+        let sd = SpectralDecayBuilder::new()
+            .grain_range_ms(5., 200.)
+            .divisions_per_octave(4)
+            .overlap(Overlap::Half)
+            .sample_rate(48000.)
+            .build()
+            .unwrap();
+        // 240 samples, the shortest grain, at 50% overlap
+        assert_eq!(sd.delay(), 240 + 120);
+
+        assert_eq!(SpectralDecayBuilder::new().grain_range_ms(200., 5.).build().err(), Some(SpectralDecayError::NoGrainSizes));
+        assert_eq!(SpectralDecayBuilder::new().grain_range_ms(0.01, 0.02).build().err(), Some(SpectralDecayError::NoGrainSizes));
+        assert_eq!(SpectralDecayBuilder::new().grain_sizes(&[6]).build().err(), Some(SpectralDecayError::InvalidGrainSize(6)));
+    }
+}
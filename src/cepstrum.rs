@@ -0,0 +1,112 @@
+use realfft::{ComplexToReal, RealToComplex};
+use rustfft::num_complex::Complex;
+use rustfft::num_traits::Zero;
+
+// keeps log magnitudes of silent bins finite
+const FLOOR: f32 = 1e-9;
+
+// splits a spectrum's log magnitudes into the spectral envelope and the fine structure on top of it,
+// by keeping only the lowest quefrencies of the real cepstrum
+// runs on the grain's own FFT plans, a spectrum of grain_size / 2 + 1 bins has a grain_size long cepstrum
+pub struct Cepstrum {
+    log_buf: Vec<Complex<f32>>,
+    cep_buf: Vec<f32>,
+    envelope: Vec<f32>,
+    fine: Vec<f32>,
+    len: usize,
+}
+
+impl Cepstrum {
+    pub fn new(max_grain_size: usize) -> Self {
+        Self {
+            log_buf: vec![Complex::zero(); max_grain_size / 2 + 1],
+            cep_buf: vec![0.; max_grain_size],
+            envelope: vec![0.; max_grain_size / 2 + 1],
+            fine: vec![0.; max_grain_size / 2 + 1],
+            len: 0,
+        }
+    }
+
+    // cutoff is the highest quefrency, in samples, that counts as envelope
+    // scratch has to be long enough for both plans
+    pub fn analyze(&mut self, bins: &[Complex<f32>], fft: &dyn RealToComplex<f32>, ifft: &dyn ComplexToReal<f32>, scratch: &mut [Complex<f32>], cutoff: usize) {
+        let grain_size = (bins.len() - 1) * 2;
+        let log_buf = &mut self.log_buf[..bins.len()];
+        let cep_buf = &mut self.cep_buf[..grain_size];
+
+        for ((y, f), x) in log_buf.iter_mut().zip(self.fine.iter_mut()).zip(bins) {
+            *f = (x.norm() + FLOOR).ln();
+            *y = Complex::new(*f, 0.);
+        }
+        ifft.process_with_scratch(log_buf, cep_buf, scratch).unwrap();
+
+        // the cepstrum is symmetric, so lifter both ends
+        let cutoff = cutoff.max(1).min(grain_size / 2);
+        for x in cep_buf[cutoff..=grain_size - cutoff].iter_mut() {
+            *x = 0.;
+        }
+        fft.process_with_scratch(cep_buf, log_buf, scratch).unwrap();
+
+        let scale = 1. / grain_size as f32;
+        for ((y, f), x) in self.envelope.iter_mut().zip(self.fine.iter_mut()).zip(log_buf.iter()) {
+            *y = x.re * scale;
+            *f -= *y;
+        }
+        self.len = bins.len();
+    }
+
+    // natural log of the envelope's magnitude in each bin of the last analyzed spectrum
+    pub fn envelope(&self) -> &[f32] {
+        &self.envelope[..self.len]
+    }
+
+    // what's left of the log magnitudes after taking out the envelope, the partials and noise
+    pub fn fine(&self) -> &[f32] {
+        &self.fine[..self.len]
+    }
+
+    // the envelope between bins, held flat past the last one
+    pub fn envelope_at(&self, bin: f32) -> f32 {
+        let envelope = self.envelope();
+        let last = envelope.len() - 1;
+        let i = (bin.max(0.) as usize).min(last);
+        if i == last {
+            return envelope[last];
+        }
+        let t = bin - i as f32;
+        envelope[i] + (envelope[i + 1] - envelope[i]) * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cepstrum_envelope() {
+        let n = 64;
+        let mut planner = realfft::RealFftPlanner::new();
+        let (fft, ifft) = (planner.plan_fft_forward(n), planner.plan_fft_inverse(n));
+        let mut scratch = vec![Complex::zero(); fft.get_scratch_len().max(ifft.get_scratch_len())];
+        let mut cepstrum = Cepstrum::new(n);
+
+        // a flat spectrum is all envelope
+        let bins = vec![Complex::new(0., 2.); n / 2 + 1];
+        cepstrum.analyze(&bins, fft.as_ref(), ifft.as_ref(), &mut scratch, 4);
+        assert_eq!(cepstrum.envelope().len(), n / 2 + 1);
+        for &x in cepstrum.envelope() {
+            assert!((x - 2f32.ln()).abs() < 1e-4, "{}", x);
+        }
+
+        // a single spike gets smeared out
+        let mut bins = vec![Complex::zero(); n / 2 + 1];
+        bins[16] = Complex::new(1., 0.);
+        cepstrum.analyze(&bins, fft.as_ref(), ifft.as_ref(), &mut scratch, 4);
+        let envelope = cepstrum.envelope();
+        assert!(envelope[16] < 0.);
+        assert!(envelope[16] > envelope[8]);
+        assert!((cepstrum.envelope_at(15.5) - (envelope[15] + envelope[16]) / 2.).abs() < 1e-6);
+        // together they make up the log magnitudes
+        assert!((envelope[16] + cepstrum.fine()[16] - (1f32 + FLOOR).ln()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::spectral_decay::{FrameBuffers, SpectralDecay};
+use std::cell::UnsafeCell;
+use std::ops::{Index, IndexMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::thread::{self, JoinHandle};
+
+// any number of channels processed in parallel, split evenly between the calling thread and worker threads
+// that are started up front so the audio thread never spawns or locks
+// the channels on a thread take turns with one set of frame buffers
+
+// at most this many threads per group, the calling one included, and no more than there are cores
+const MAX_THREADS: usize = 4;
+
+const IDLE: u8 = 0;
+const RUNNING: u8 = 1;
+const DONE: u8 = 2;
+const EXIT: u8 = 3;
+// a channel panicked, the thread lives on and the panic is passed on to the caller of process()
+const PANICKED: u8 = 4;
+
+pub struct ChannelIo<'a> {
+    pub input: &'a [f32],
+    pub sidechain: Option<&'a [f32]>,
+    pub output: &'a mut [f32],
+}
+
+impl ChannelIo<'_> {
+    fn process(&mut self, sd: &mut SpectralDecay, buffers: &mut FrameBuffers) {
+        sd.lend_frame_buffers(buffers, |sd| match self.sidechain {
+            Some(sidechain) => sd.process_sidechain(self.input, sidechain, self.output),
+            None => sd.process(self.input, self.output)
+        })
+    }
+}
+
+// a ChannelIo with the lifetime erased, only dereferenced while process() is waiting on it
+struct Job {
+    input: *const f32,
+    sidechain: *const f32,
+    output: *mut f32,
+    len: usize,
+}
+
+impl Job {
+    const EMPTY: Job = Job {
+        input: std::ptr::null(),
+        sidechain: std::ptr::null(),
+        output: std::ptr::null_mut(),
+        len: 0,
+    };
+}
+
+struct Shared {
+    state: AtomicU8,
+    // one of each per channel, the jobs are rewritten in place every block
+    jobs: UnsafeCell<Vec<Job>>,
+    sds: UnsafeCell<Vec<SpectralDecay>>,
+    buffers: UnsafeCell<FrameBuffers>,
+}
+
+// jobs, sds and buffers are only touched by the worker while state is RUNNING,
+// and by the owner of the ChannelGroup otherwise
+unsafe impl Sync for Shared {}
+unsafe impl Send for Shared {}
+
+fn worker(shared: Arc<Shared>) {
+    loop {
+        match shared.state.load(Ordering::Acquire) {
+            RUNNING => {
+                // always reporting back, or the caller would wait forever
+                let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+                    let buffers = &mut *shared.buffers.get();
+                    for (job, sd) in (*shared.jobs.get()).iter().zip((*shared.sds.get()).iter_mut()) {
+                        let sidechain = if job.sidechain.is_null() {
+                            None
+                        } else {
+                            Some(std::slice::from_raw_parts(job.sidechain, job.len))
+                        };
+                        ChannelIo {
+                            input: std::slice::from_raw_parts(job.input, job.len),
+                            sidechain,
+                            output: std::slice::from_raw_parts_mut(job.output, job.len),
+                        }.process(sd, buffers);
+                    }
+                }));
+                shared.state.store(if result.is_ok() { DONE } else { PANICKED }, Ordering::Release);
+            },
+            EXIT => return,
+            // parking can wake up spuriously, so always recheck
+            _ => thread::park()
+        }
+    }
+}
+
+struct Worker {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    fn new(mut sds: Vec<SpectralDecay>) -> Self {
+        let buffers = SpectralDecay::share_frame_buffers(&mut sds);
+        let shared = Arc::new(Shared {
+            state: AtomicU8::new(IDLE),
+            jobs: UnsafeCell::new(sds.iter().map(|_| Job::EMPTY).collect()),
+            sds: UnsafeCell::new(sds),
+            buffers: UnsafeCell::new(buffers),
+        });
+        let thread = {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("spectral-decay channel".into())
+                .spawn(move || worker(shared))
+                .expect("failed to spawn channel thread")
+        };
+
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    // one io per channel of the worker's, already checked, a panic after the first start() would leave that one running
+    fn start(&self, ios: &mut [ChannelIo]) {
+        let jobs = unsafe { &mut *self.shared.jobs.get() };
+        for (job, io) in jobs.iter_mut().zip(ios.iter_mut()) {
+            *job = Job {
+                input: io.input.as_ptr(),
+                sidechain: io.sidechain.map_or(std::ptr::null(), |x| x.as_ptr()),
+                output: io.output.as_mut_ptr(),
+                len: io.input.len(),
+            };
+        }
+        self.shared.state.store(RUNNING, Ordering::Release);
+        self.thread.as_ref().unwrap().thread().unpark();
+    }
+
+    // true if a channel panicked
+    fn wait(&self) -> bool {
+        // every thread gets about the same amount of work, so it shouldn't be long
+        let state = loop {
+            match self.shared.state.load(Ordering::Acquire) {
+                RUNNING => std::hint::spin_loop(),
+                state => break state,
+            }
+        };
+        self.shared.state.store(IDLE, Ordering::Relaxed);
+        state == PANICKED
+    }
+}
+
+// waits for the started workers when dropped, so even a panic on the calling thread
+// can't free the caller's buffers while they're still being written to
+struct Running<'a>(&'a [Worker]);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let panicked = self.0.iter().fold(false, |panicked, worker| worker.wait() || panicked);
+        if panicked && !thread::panicking() {
+            panic!("a channel thread panicked");
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.shared.state.store(EXIT, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+pub struct ChannelGroup {
+    // the first channels_per_thread channels, processed on the calling thread
+    local: Vec<SpectralDecay>,
+    local_buffers: FrameBuffers,
+    // the rest, channels_per_thread to a worker, fewer on the last one
+    workers: Vec<Worker>,
+    channels_per_thread: usize,
+    len: usize,
+}
+
+impl ChannelGroup {
+    // panics on no channels
+    pub fn new(channels: Vec<SpectralDecay>) -> Self {
+        let len = channels.len();
+        assert!(len > 0, "no channels");
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        let threads = len.min(MAX_THREADS).min(cores);
+        let channels_per_thread = (len + threads - 1) / threads;
+
+        let mut channels = channels.into_iter();
+        let mut local: Vec<_> = channels.by_ref().take(channels_per_thread).collect();
+        let local_buffers = SpectralDecay::share_frame_buffers(&mut local);
+        let mut workers = Vec::new();
+        loop {
+            let sds: Vec<_> = channels.by_ref().take(channels_per_thread).collect();
+            if sds.is_empty() {
+                break;
+            }
+            workers.push(Worker::new(sds));
+        }
+        Self {
+            local,
+            local_buffers,
+            workers,
+            channels_per_thread,
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    // one ChannelIo per channel, in order
+    pub fn process(&mut self, channels: &mut [ChannelIo]) {
+        assert_eq!(channels.len(), self.len());
+        for io in channels.iter() {
+            assert_eq!(io.input.len(), io.output.len());
+            if let Some(sidechain) = io.sidechain {
+                assert_eq!(sidechain.len(), io.input.len());
+            }
+        }
+        let (first, rest) = channels.split_at_mut(self.local.len());
+
+        for (worker, ios) in self.workers.iter().zip(rest.chunks_mut(self.channels_per_thread)) {
+            worker.start(ios);
+        }
+        let _running = Running(&self.workers);
+        for (io, sd) in first.iter_mut().zip(self.local.iter_mut()) {
+            io.process(sd, &mut self.local_buffers);
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut SpectralDecay> {
+        // the workers are idle whenever we're not inside process()
+        self.local.iter_mut().chain(self.workers.iter_mut().flat_map(|w| unsafe { &mut *w.shared.sds.get() }.iter_mut()))
+    }
+}
+
+impl Index<usize> for ChannelGroup {
+    type Output = SpectralDecay;
+
+    fn index(&self, index: usize) -> &SpectralDecay {
+        let (thread, i) = (index / self.channels_per_thread, index % self.channels_per_thread);
+        match thread {
+            _ if index >= self.len() => panic!("channel {} out of range", index),
+            0 => &self.local[i],
+            // the workers are idle whenever we're not inside process()
+            _ => unsafe { &(*self.workers[thread - 1].shared.sds.get())[i] },
+        }
+    }
+}
+
+impl IndexMut<usize> for ChannelGroup {
+    fn index_mut(&mut self, index: usize) -> &mut SpectralDecay {
+        let (thread, i) = (index / self.channels_per_thread, index % self.channels_per_thread);
+        match thread {
+            _ if index >= self.len() => panic!("channel {} out of range", index),
+            0 => &mut self.local[i],
+            _ => unsafe { &mut (*self.workers[thread - 1].shared.sds.get())[i] },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_group() {
+        let mut sd = SpectralDecay::new(&[32, 64]);
+        let mut group = ChannelGroup::new((0..3).map(|_| SpectralDecay::new(&[32, 64])).collect());
+        assert_eq!(group.len(), 3);
+
+        let input: Vec<_> = (0..256).map(|x| (x as f32 * 0.1).sin()).collect();
+        let mut expected = vec![0.; 256];
+        let mut out0 = vec![0.; 256];
+        let mut out1 = vec![0.; 256];
+        let mut out2 = vec![0.; 256];
+
+        for i in 0..4 {
+            let range = i * 64..(i + 1) * 64;
+            sd.process(&input[range.clone()], &mut expected[range.clone()]);
+            group.process(&mut [
+                ChannelIo { input: &input[range.clone()], sidechain: None, output: &mut out0[range.clone()] },
+                ChannelIo { input: &input[range.clone()], sidechain: None, output: &mut out1[range.clone()] },
+                ChannelIo { input: &input[range.clone()], sidechain: None, output: &mut out2[range.clone()] },
+            ]);
+        }
+
+        assert_eq!(out0, expected);
+        assert_eq!(out1, expected);
+        assert_eq!(out2, expected);
+        assert_eq!(group[2].delay(), sd.delay());
+        assert_eq!(group.iter_mut().count(), 3);
+    }
+
+    #[test]
+    fn channel_group_panic() {
+        let worker = Worker::new(vec![SpectralDecay::new(&[32, 64])]);
+
+        // as if the worker's channel had panicked, which comes back here instead of hanging
+        worker.shared.state.store(PANICKED, Ordering::Release);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| drop(Running(std::slice::from_ref(&worker)))));
+        assert!(result.is_err());
+
+        // and the worker is still there for the next block
+        let input = vec![0.; 64];
+        let mut output = vec![1.; 64];
+        worker.start(&mut [ChannelIo { input: &input, sidechain: None, output: &mut output }]);
+        assert!(!worker.wait());
+        assert!(output.iter().all(|&x| x == 0.));
+    }
+
+    #[test]
+    fn channel_group_many() {
+        let input: Vec<_> = (0..256).map(|x| (x as f32 * 0.1).sin()).collect();
+        let mut expected = vec![0.; 256];
+        SpectralDecay::new(&[32, 64]).process(&input, &mut expected);
+
+        // a few threads with several channels each, not one per channel
+        let mut group = ChannelGroup::new((0..16).map(|_| SpectralDecay::new(&[32, 64])).collect());
+        assert!(group.workers.len() < MAX_THREADS);
+        assert_eq!(group.iter_mut().count(), 16);
+
+        let mut outputs = vec![vec![0.; 256]; 16];
+        let mut channels: Vec<_> = outputs.iter_mut()
+            .map(|output| ChannelIo { input: &input, sidechain: None, output })
+            .collect();
+        group.process(&mut channels);
+        for output in outputs.iter() {
+            assert_eq!(*output, expected);
+        }
+        assert_eq!(group[15].delay(), group[0].delay());
+    }
+}
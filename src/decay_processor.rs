@@ -0,0 +1,622 @@
+use crate::spectral_decay::{Component, PhaseMode, SpectralDecayParameters};
+use crate::spectral_processor::{FrameCtx, SpectralProcessor};
+use crate::phase_vocoder::PhaseVocoder;
+use crate::spectral_delay::SpectralDelay;
+use crate::hpss::Hpss;
+use crate::onset::SpectralFlux;
+use crate::rng::{new_rng, seeded_rng};
+use crate::partials::PartialTracker;
+use crate::denormal::flush;
+use rustfft::num_complex::Complex;
+use rustfft::num_traits::Zero;
+use random_fast_rng::{FastRng, Random};
+use std::f32::consts::PI;
+
+// one pole smoothing coefficient reaching ~63% of the way in this many frames
+fn frames_to_coef(frames: f32) -> f32 {
+    if frames <= 0. {
+        1.
+    } else {
+        1. - (-1. / frames).exp()
+    }
+}
+
+// levels of crush on a dB scale are spread over this range below the loudest bin
+const CRUSH_DB_RANGE: f32 = 60.;
+
+// loss_db thresholds go from this far below full scale up to full scale
+pub(crate) const LOSS_DB_RANGE: f32 = 96.;
+
+// at full melt, the most a frozen bin loses of its magnitude per frame, and the most its phase wanders
+const MELT_DECAY: f32 = 0.05;
+const MELT_DRIFT: f32 = PI / 4.;
+
+// width of the band each bin of the vocoder's sidechain envelope is averaged over
+const VOCODE_OCTAVES: f32 = 1. / 3.;
+
+// rescale to magnitude r, silent bins have no phase to keep so they get a random one like freeze
+fn set_norm(x: &mut Complex<f32>, r: f32, rand: &mut impl FnMut() -> f32) {
+    let prev = x.norm();
+    if prev > 0. {
+        *x *= r / prev;
+    } else {
+        *x = Complex::from_polar(r, 2. * PI * rand());
+    }
+}
+
+// the default spectrum stage: partial tracking, freeze and melt, accumulation, pitch, spread, spectral delay, blur, decay, tilt, morphing, vocoding, ducking, compression, scrambling, glitch, loss, fuzz and crush
+pub struct DecayProcessor {
+    // partials and the grain they were tracked in, None when not tracking
+    tracking: Option<usize>,
+    tracker: PartialTracker,
+    // latched magnitudes and the grain they were taken from, None when not frozen
+    frozen: Option<usize>,
+    freeze_buf: Vec<f32>,
+    // phases they were last resynthesized with
+    freeze_phase: Vec<f32>,
+    // loudest magnitude of every bin so far and the grain they're from, None when not accumulating
+    accumulating: Option<usize>,
+    accumulate_buf: Vec<f32>,
+    // grain the vocoder state belongs to, None when not shifting
+    shifting: Option<usize>,
+    vocoder: PhaseVocoder,
+    // the spectrum before spreading, scratch
+    spread_buf: Vec<Complex<f32>>,
+    // grain the delay lines belong to, None when not delaying
+    delaying: Option<usize>,
+    delay: SpectralDelay,
+    // smoothed magnitudes and the grain they belong to, None when not blurring
+    blurring: Option<usize>,
+    blur_buf: Vec<f32>,
+    // decaying peak magnitudes and the grain they belong to, None when not decaying
+    decaying: Option<usize>,
+    decay_buf: Vec<f32>,
+    // grain the flux history belongs to, None when the loss isn't adaptive
+    flux_grain: Option<usize>,
+    flux: SpectralFlux,
+    // grain the separation history belongs to, None when processing both components
+    separating: Option<usize>,
+    hpss: Hpss,
+    hpss_mask: Vec<f32>,
+    // the component that skips processing, added back at the end
+    bypass: Vec<Complex<f32>>,
+    // per-bin gain of the compressor and the grain it belongs to, None when not compressing
+    compressing: Option<usize>,
+    comp_gain: Vec<f32>,
+    // per-bin gain of the loss gate and the grain it belongs to
+    gate_grain: Option<usize>,
+    gate: Vec<f32>,
+    // held value of every glitched bin and how many more frames it's held for, and the grain they belong to
+    holding: Option<usize>,
+    hold: Vec<(Complex<f32>, usize)>,
+    // gain every glitched bin is fading back to 1 from, and the grain they belong to
+    bursting: Option<usize>,
+    burst: Vec<f32>,
+    // running sum of the sidechain's magnitudes, and its envelope, both scratch for vocoding
+    vocode_sum: Vec<f32>,
+    vocode_env: Vec<f32>,
+    rng: FastRng,
+}
+
+impl DecayProcessor {
+    pub fn new(max_grain_size: usize) -> Self {
+        let num_bins = max_grain_size / 2 + 1;
+        Self {
+            tracking: None,
+            tracker: PartialTracker::new(max_grain_size),
+            frozen: None,
+            freeze_buf: vec![0.; num_bins],
+            freeze_phase: vec![0.; num_bins],
+            accumulating: None,
+            accumulate_buf: vec![0.; num_bins],
+            shifting: None,
+            vocoder: PhaseVocoder::new(num_bins),
+            spread_buf: vec![Complex::zero(); num_bins],
+            delaying: None,
+            delay: SpectralDelay::new(num_bins),
+            blurring: None,
+            blur_buf: vec![0.; num_bins],
+            decaying: None,
+            decay_buf: vec![0.; num_bins],
+            flux_grain: None,
+            flux: SpectralFlux::new(num_bins),
+            separating: None,
+            hpss: Hpss::new(num_bins),
+            hpss_mask: vec![0.; num_bins],
+            bypass: vec![Complex::zero(); num_bins],
+            compressing: None,
+            comp_gain: vec![1.; num_bins],
+            gate_grain: None,
+            gate: vec![1.; num_bins],
+            holding: None,
+            hold: vec![(Complex::zero(), 0); num_bins],
+            bursting: None,
+            burst: vec![1.; num_bins],
+            vocode_sum: vec![0.; num_bins + 1],
+            vocode_env: vec![0.; num_bins],
+            rng: new_rng(),
+        }
+    }
+}
+
+impl SpectralProcessor for DecayProcessor {
+    fn process(&mut self, bins: &mut [Complex<f32>], ctx: FrameCtx) {
+        let SpectralDecayParameters {
+            fuzz,
+            fuzz_slope,
+            loss,
+            glitch_freq,
+            glitch_gain,
+            glitch_tilt,
+            glitch_hold,
+            glitch_decay,
+            freeze,
+            melt,
+            accumulate,
+            partials,
+            partial_detune,
+            partial_decay,
+            pitch,
+            spread,
+            gate_attack,
+            gate_release,
+            spectral_delay,
+            spectral_delay_feedback,
+            blur,
+            decay,
+            duck,
+            morph,
+            vocode,
+            glitch_synced,
+            phase_mode,
+            component,
+            compress_threshold,
+            compress_ratio,
+            compress_attack,
+            compress_release,
+            tilt,
+            tilt_center,
+            scramble,
+            scramble_range,
+            crush,
+            crush_db,
+            low_cut,
+            high_cut,
+            adaptive_loss,
+            loss_invert,
+            loss_db,
+            ..
+        } = *ctx.params;
+
+        // raise the loss threshold on steady material and drop it on transients, judged from the input
+        let loss = if adaptive_loss == 0. {
+            self.flux_grain = None;
+            loss
+        } else {
+            if self.flux_grain != Some(ctx.grain_index) {
+                self.flux_grain = Some(ctx.grain_index);
+                self.flux.clear();
+            }
+            let steadiness = 1. - self.flux.process(bins);
+            let adapted = loss * 2. * steadiness;
+            (loss + (adapted - loss) * adaptive_loss).min(1.)
+        };
+
+        // split off the component that isn't processed
+        if component == Component::Both {
+            self.separating = None;
+        } else {
+            if self.separating != Some(ctx.grain_index) {
+                self.separating = Some(ctx.grain_index);
+                self.hpss.clear();
+            }
+            let mask = &mut self.hpss_mask[..bins.len()];
+            self.hpss.harmonic_mask(bins, mask);
+
+            for ((x, y), &m) in bins.iter_mut().zip(self.bypass.iter_mut()).zip(mask.iter()) {
+                let m = if component == Component::Harmonic { m } else { 1. - m };
+                *y = *x * (1. - m);
+                *x *= m;
+            }
+        }
+
+        let rng = &mut self.rng;
+        let mut rand = || { rng.gen::<u32>() as f32 / u32::MAX as f32 };
+
+        // the partials carry their own phases, so they're transposed here rather than by the vocoder
+        let max_partials = partials as usize;
+        if max_partials == 0 {
+            self.tracking = None;
+        } else {
+            // partials from another grain size are at the wrong bins
+            if self.tracking != Some(ctx.grain_index) {
+                self.tracking = Some(ctx.grain_index);
+                self.tracker.clear();
+            }
+            self.tracker.track(bins, max_partials, partial_detune, &mut rand);
+            self.tracker.synthesize(bins, ctx.fft_size, ctx.hop, 2f32.powf(pitch / 12.), partial_decay);
+        }
+
+        if !freeze {
+            self.frozen = None;
+        } else {
+            let freeze_buf = &mut self.freeze_buf[..bins.len()];
+            let freeze_phase = &mut self.freeze_phase[..bins.len()];
+
+            // latch on the first frozen frame, and again if the grain size changes underneath
+            if self.frozen != Some(ctx.grain_index) {
+                self.frozen = Some(ctx.grain_index);
+                for ((y, p), x) in freeze_buf.iter_mut().zip(freeze_phase.iter_mut()).zip(bins.iter()) {
+                    *y = x.norm();
+                    *p = x.arg();
+                }
+            }
+
+            // resynthesize the latched magnitudes with random phase,
+            // or melting, carry the phases on at each bin's frequency with a random walk on top, and let the magnitudes go
+            let advance = 2. * PI * ctx.hop as f32 / ctx.fft_size as f32;
+            for (k, ((x, r), p)) in bins.iter_mut().zip(freeze_buf.iter_mut()).zip(freeze_phase.iter_mut()).enumerate() {
+                if melt == 0. {
+                    *p = 2. * PI * rand();
+                } else {
+                    *r = flush(*r * (1. - melt * MELT_DECAY * rand()));
+                    *p = (*p + k as f32 * advance + melt * MELT_DRIFT * (2. * rand() - 1.)) % (2. * PI);
+                }
+                *x = Complex::from_polar(*r, *p);
+            }
+        }
+
+        if !accumulate {
+            self.accumulating = None;
+        } else {
+            let accumulate_buf = &mut self.accumulate_buf[..bins.len()];
+
+            // start over from silence when turned on, cleared or the grain size changes
+            if self.accumulating != Some(ctx.grain_index) || ctx.accumulate_clear {
+                self.accumulating = Some(ctx.grain_index);
+                for y in accumulate_buf.iter_mut() {
+                    *y = 0.;
+                }
+            }
+
+            // resynthesized with random phase like freeze, since the peaks come from frames all over the place
+            for (x, y) in bins.iter_mut().zip(accumulate_buf.iter_mut()) {
+                *y = y.max(x.norm());
+                *x = Complex::from_polar(*y, 2. * PI * rand());
+            }
+        }
+
+        if pitch == 0. || self.tracking.is_some() {
+            self.shifting = None;
+        } else {
+            // phases from another grain size or from before shifting was enabled are meaningless
+            if self.shifting != Some(ctx.grain_index) {
+                self.shifting = Some(ctx.grain_index);
+                self.vocoder.reset();
+            }
+            let ratio = 2f32.powf(pitch / 12.);
+            self.vocoder.pitch_shift(bins, ctx.fft_size, ctx.hop, ratio);
+        }
+
+        // each bin reads from spread times lower, interpolating magnitude and taking the nearest phase
+        // inharmonic, since every partial moves by a different number of Hz
+        if spread != 1. && spread > 0. {
+            let spread_buf = &mut self.spread_buf[..bins.len()];
+            spread_buf.copy_from_slice(bins);
+            let last = bins.len() - 1;
+
+            for (k, x) in bins.iter_mut().enumerate() {
+                let from = k as f32 / spread;
+                let i = from as usize;
+                *x = if i >= last {
+                    Complex::zero()
+                } else {
+                    let t = from - i as f32;
+                    let r = spread_buf[i].norm() * (1. - t) + spread_buf[i + 1].norm() * t;
+                    let nearest = spread_buf[if t < 0.5 { i } else { i + 1 }];
+                    Complex::from_polar(r, nearest.arg())
+                };
+            }
+        }
+
+        if spectral_delay == 0. {
+            self.delaying = None;
+        } else {
+            // don't replay bins from another grain size or from the last time the delay was on
+            if self.delaying != Some(ctx.grain_index) {
+                self.delaying = Some(ctx.grain_index);
+                self.delay.clear();
+            }
+            self.delay.process(bins, spectral_delay, spectral_delay_feedback);
+        }
+
+        if blur == 0. {
+            self.blurring = None;
+        } else {
+            let blur_buf = &mut self.blur_buf[..bins.len()];
+            if self.blurring != Some(ctx.grain_index) {
+                self.blurring = Some(ctx.grain_index);
+                for (y, x) in blur_buf.iter_mut().zip(bins.iter()) {
+                    *y = x.norm();
+                }
+            }
+            // smear magnitudes across frames, keeping the current phase so it doesn't cancel out
+            for (x, y) in bins.iter_mut().zip(blur_buf.iter_mut()) {
+                *y = flush(x.norm() * (1. - blur) + *y * blur);
+                set_norm(x, *y, &mut rand);
+            }
+        }
+
+        if decay == 0. {
+            self.decaying = None;
+        } else {
+            let decay_buf = &mut self.decay_buf[..bins.len()];
+            if self.decaying != Some(ctx.grain_index) {
+                self.decaying = Some(ctx.grain_index);
+                for y in decay_buf.iter_mut() {
+                    *y = 0.;
+                }
+            }
+            // each bin holds its peak and lets it die away exponentially
+            for (x, y) in bins.iter_mut().zip(decay_buf.iter_mut()) {
+                *y = flush(x.norm().max(*y * decay));
+                set_norm(x, *y, &mut rand);
+            }
+        }
+
+        if tilt != 0. {
+            let bin_freq = ctx.sample_rate / ctx.fft_size as f32;
+            for (k, x) in bins.iter_mut().enumerate() {
+                // DC has no octave, treat it as half a bin
+                let freq = (k as f32).max(0.5) * bin_freq;
+                *x *= 10f32.powf(tilt * (freq / tilt_center).log2() / 20.);
+            }
+        }
+
+        // the sidechain's magnitudes with the input's phases
+        if let (Some(sidechain), true) = (ctx.sidechain, morph > 0.) {
+            for (x, sc) in bins.iter_mut().zip(sidechain) {
+                let r = x.norm() * (1. - morph) + sc.norm() * morph;
+                set_norm(x, r, &mut rand);
+            }
+        }
+
+        // the input through the sidechain's spectral envelope, loudest band at unity
+        if let (Some(sidechain), true) = (ctx.sidechain, vocode > 0.) {
+            let sum = &mut self.vocode_sum[..bins.len() + 1];
+            for (k, sc) in sidechain.iter().enumerate() {
+                sum[k + 1] = sum[k] + sc.norm();
+            }
+
+            let envelope = &mut self.vocode_env[..bins.len()];
+            let spread = 2f32.powf(VOCODE_OCTAVES / 2.);
+            for (k, y) in envelope.iter_mut().enumerate() {
+                let lo = ((k as f32 / spread) as usize).min(k);
+                let hi = ((k as f32 * spread).ceil() as usize).max(k + 1).min(bins.len());
+                *y = (sum[hi] - sum[lo]) / (hi - lo) as f32;
+            }
+
+            let max_env = envelope.iter().fold(0., |max: f32, &y| y.max(max));
+            for (x, &y) in bins.iter_mut().zip(envelope.iter()) {
+                let gain = if max_env > 0. { y / max_env } else { 0. };
+                *x *= 1. - vocode + vocode * gain;
+            }
+        }
+
+        // spectral subtraction of the sidechain
+        if let (Some(sidechain), true) = (ctx.sidechain, duck > 0.) {
+            for (x, sc) in bins.iter_mut().zip(sidechain) {
+                let r = x.norm();
+                if r > 0. {
+                    *x *= (r - duck * sc.norm()).max(0.) / r;
+                }
+            }
+        }
+
+        let mut max_amp = 0.;
+
+        for x in bins.iter() {
+            max_amp = x.norm().max(max_amp);
+        }
+
+        if compress_ratio <= 1. {
+            self.compressing = None;
+        } else {
+            let comp_gain = &mut self.comp_gain[..bins.len()];
+            if self.compressing != Some(ctx.grain_index) {
+                self.compressing = Some(ctx.grain_index);
+                for g in comp_gain.iter_mut() {
+                    *g = 1.;
+                }
+            }
+            let threshold = 10f32.powf(compress_threshold / 20.) * max_amp;
+            let attack = frames_to_coef(compress_attack);
+            let release = frames_to_coef(compress_release);
+
+            for (x, g) in bins.iter_mut().zip(comp_gain.iter_mut()) {
+                let over = x.norm() / threshold;
+                let target = if over > 1. { over.powf(1. / compress_ratio - 1.) } else { 1. };
+                *g += (target - *g) * if target < *g { attack } else { release };
+                *x *= *g;
+            }
+
+            max_amp = bins.iter().fold(0., |max, x| x.norm().max(max));
+        }
+
+        // swap bins with random neighbors
+        if scramble > 0. {
+            let range = scramble_range.max(1.) as usize;
+            let num_bins = bins.len();
+            for k in 0..num_bins {
+                if rand() < scramble {
+                    let start = k.saturating_sub(range);
+                    let end = (k + range + 1).min(num_bins);
+                    let j = start + ((rand() * (end - start) as f32) as usize).min(end - start - 1);
+                    bins.swap(k, j);
+                }
+            }
+        }
+
+        let gate = &mut self.gate[..bins.len()];
+        if self.gate_grain != Some(ctx.grain_index) {
+            self.gate_grain = Some(ctx.grain_index);
+            for g in gate.iter_mut() {
+                *g = 1.;
+            }
+        }
+        // synced glitches land all at once instead of trickling in every frame
+        let glitch_prob = match (glitch_synced, ctx.glitch_trigger) {
+            (false, _) => glitch_freq / 8.,
+            (true, true) => glitch_freq,
+            (true, false) => 0.
+        };
+
+        let hold = &mut self.hold[..bins.len()];
+        if glitch_hold < 1. {
+            self.holding = None;
+        } else if self.holding != Some(ctx.grain_index) {
+            self.holding = Some(ctx.grain_index);
+            for h in hold.iter_mut() {
+                h.1 = 0;
+            }
+        }
+        let holding = self.holding.is_some();
+
+        let burst = &mut self.burst[..bins.len()];
+        if glitch_decay <= 0. {
+            self.bursting = None;
+        } else if self.bursting != Some(ctx.grain_index) {
+            self.bursting = Some(ctx.grain_index);
+            for b in burst.iter_mut() {
+                *b = 1.;
+            }
+        }
+        let bursting = self.bursting.is_some();
+        let burst_release = frames_to_coef(glitch_decay);
+
+        // a weight from 1 - glitch_tilt at DC to 1 + glitch_tilt at nyquist
+        let last = (bins.len() - 1).max(1) as f32;
+        let glitch_weight = |k: usize| 1. + glitch_tilt * (2. * k as f32 / last - 1.);
+
+        // random phases make overlapping frames add up in power instead of amplitude, so make up the difference
+        let phase_gain = match phase_mode {
+            PhaseMode::Whisper => (ctx.grain_size as f32 / ctx.hop as f32).sqrt(),
+            _ => 1.
+        };
+
+        let attack = frames_to_coef(gate_attack);
+        let release = frames_to_coef(gate_release);
+        // inverted, the top of the range is lost instead of the bottom
+        let level = |t: f32| match (loss_db, t > 0.) {
+            (false, _) => t * max_amp,
+            (true, true) => ctx.full_scale * 10f32.powf(LOSS_DB_RANGE * (t - 1.) / 20.),
+            (true, false) => 0.,
+        };
+        let threshold = if loss_invert { level(1. - loss) } else { level(loss) };
+
+        // quantize relative to the loudest bin, off below one level
+        let levels = if max_amp > 0. { crush.floor() } else { 0. };
+
+        // bins outside of the focus range pass through
+        let bin_freq = ctx.sample_rate / ctx.fft_size as f32;
+        let focus = (low_cut / bin_freq).ceil() as usize..=(high_cut / bin_freq).min(bins.len() as f32) as usize;
+
+        for (k, (((x, g), h), b)) in bins.iter_mut().zip(gate.iter_mut()).zip(hold.iter_mut()).zip(burst.iter_mut()).enumerate() {
+            if !focus.contains(&k) {
+                continue;
+            }
+            let lost = if loss_invert { x.norm() > threshold } else { x.norm() < threshold };
+            if lost {
+                *g -= *g * release;
+            } else {
+                *g += (1. - *g) * attack;
+            }
+            // what's left of the last glitch on this bin
+            let swell = if bursting {
+                *b += (1. - *b) * burst_release;
+                *b
+            } else {
+                1.
+            };
+
+            if holding && h.1 > 0 {
+                h.1 -= 1;
+                *x = h.0;
+            } else if rand() < glitch_prob * glitch_weight(k) {
+                let k = rand();
+                let gain = k * k * glitch_gain;
+                *x *= gain;
+                // the same value every frame, so its phase stops advancing and it whistles at the hop rate
+                if holding {
+                    *h = (*x, (rand() * glitch_hold) as usize);
+                }
+                if bursting {
+                    *b = gain;
+                }
+            } else if *g == 0. {
+                *x = Complex::zero();
+            } else if fuzz > 0. || phase_mode != PhaseMode::Normal {
+                let (r, theta) = x.to_polar();
+                let theta = match phase_mode {
+                    PhaseMode::Normal => {
+                        let delta = 2. * PI * rand();
+                        let slope = if fuzz_slope == 0. { 1. } else { (k as f32 / last).powf(fuzz_slope) };
+                        theta + delta * fuzz * slope
+                    },
+                    // alternating signs center every frame's pulse in the grain, giving a monotone buzz at the hop rate
+                    PhaseMode::Robot => PI * k as f32,
+                    PhaseMode::Whisper => 2. * PI * rand(),
+                };
+
+                *x = Complex::from_polar(r * *g * phase_gain * swell, theta);
+            } else {
+                *x *= *g * swell;
+            }
+
+            if levels > 0. {
+                let r = x.norm() / max_amp;
+                let quantized = if crush_db {
+                    let db = 20. * r.log10();
+                    if db < -CRUSH_DB_RANGE {
+                        0.
+                    } else {
+                        let step = CRUSH_DB_RANGE / levels;
+                        10f32.powf((db / step).round() * step / 20.)
+                    }
+                } else {
+                    (r * levels).round() / levels
+                };
+                if r > 0. {
+                    *x *= quantized / r;
+                }
+            }
+        }
+
+        if self.separating.is_some() {
+            for (x, y) in bins.iter_mut().zip(self.bypass.iter()) {
+                *x += *y;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        // everything is lazily reinitialized on the next frame
+        self.tracking = None;
+        self.frozen = None;
+        self.accumulating = None;
+        self.shifting = None;
+        self.delaying = None;
+        self.blurring = None;
+        self.decaying = None;
+        self.flux_grain = None;
+        self.separating = None;
+        self.compressing = None;
+        self.gate_grain = None;
+        self.holding = None;
+        self.bursting = None;
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = seeded_rng(seed);
+    }
+}
@@ -0,0 +1,44 @@
+use rustfft::num_complex::Complex;
+use rustfft::num_traits::Zero;
+
+// values below this are flushed to zero, far under anything audible but also far above the denormal range,
+// which feedback paths decaying through silence would otherwise spend a long, slow time in
+const FLUSH: f32 = 1e-15;
+
+pub fn flush(x: f32) -> f32 {
+    if x.abs() < FLUSH { 0. } else { x }
+}
+
+pub fn flush_complex(x: Complex<f32>) -> Complex<f32> {
+    Complex::new(flush(x.re), flush(x.im))
+}
+
+// zeroes NaN and infinite bins and flushes tiny ones, returns whether there were any non-finite ones
+pub fn sanitize(bins: &mut [Complex<f32>]) -> bool {
+    let mut non_finite = false;
+    for x in bins.iter_mut() {
+        if x.re.is_finite() && x.im.is_finite() {
+            *x = flush_complex(*x);
+        } else {
+            *x = Complex::zero();
+            non_finite = true;
+        }
+    }
+    non_finite
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_bins() {
+        let mut bins = [Complex::new(1., -1.), Complex::new(1e-30, 0.5), Complex::new(f32::NAN, 0.), Complex::new(0., f32::INFINITY)];
+        assert!(sanitize(&mut bins));
+        assert_eq!(bins, [Complex::new(1., -1.), Complex::new(0., 0.5), Complex::zero(), Complex::zero()]);
+
+        assert!(!sanitize(&mut bins));
+        assert_eq!(flush(-1e-20), 0.);
+        assert_eq!(flush(1e-3), 1e-3);
+    }
+}
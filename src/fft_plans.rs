@@ -0,0 +1,44 @@
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::{Arc, Mutex};
+
+// FFT plans shared by every instance in the process, so a session full of them only plans and stores each size once
+// only locked while constructing, plans are immutable and used without it
+
+type Plans = (Arc<dyn RealToComplex<f32>>, Arc<dyn ComplexToReal<f32>>);
+
+static CACHE: Mutex<Vec<(usize, Plans)>> = Mutex::new(Vec::new());
+
+// forward and inverse plans of size n, planned on first use
+pub(crate) fn plans(n: usize) -> Plans {
+    #[cfg(feature = "rt-check")]
+    crate::rt_check::assert_off_audio_path("locking the FFT plan cache");
+    // a panic while holding the lock can't leave the list half written
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, plans)) = cache.iter().find(|(size, _)| *size == n) {
+        return plans.clone();
+    }
+    let mut planner = RealFftPlanner::new();
+    let plans = (planner.plan_fft_forward(n), planner.plan_fft_inverse(n));
+    cache.push((n, plans.clone()));
+    plans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_shared() {
+        // a size nothing else uses
+        let n = 4 * 997;
+        let (fft, ifft) = plans(n);
+        assert_eq!(fft.len(), n);
+        assert_eq!(ifft.len(), n);
+
+        // the cache's copy, this one and the next
+        let (again, _) = plans(n);
+        assert_eq!(Arc::strong_count(&fft), 3);
+        drop(again);
+        assert_eq!(Arc::strong_count(&fft), 2);
+    }
+}
@@ -3,35 +3,76 @@
 // but also we want the sizes to be easy to FFT, so pick the prime factors carefully
 // RustFFT loves lots of 2's and 3's and can tolerate some 5, 7, 11
 
+use crate::spectral_decay::Overlap;
+
+// which sizes count as easy to FFT: powers of 2 and 3,
+// times up to max_primes factors from allowed_primes, and divisible by require_divisible_by (0 is the same as 1)
+#[derive(Debug, Clone)]
+pub struct SizeGenOptions {
+    pub max_primes: usize,
+    pub allowed_primes: Vec<u64>,
+    pub require_divisible_by: u64,
+}
+
+impl Default for SizeGenOptions {
+    fn default() -> Self {
+        Self {
+            max_primes: 2,
+            allowed_primes: vec![5, 7, 11],
+            // SpectralDecay needs grain sizes divisible by 4
+            require_divisible_by: 4,
+        }
+    }
+}
+
+// every product of up to count factors from primes, without repeats
+fn prime_products(primes: &[u64], count: usize) -> Vec<u64> {
+    let mut products = vec![1];
+    if count == 0 {
+        return products;
+    }
+    for (i, &p) in primes.iter().enumerate() {
+        // only use primes from here on so each combination shows up once
+        products.extend(prime_products(&primes[i..], count - 1).into_iter().map(|x| x * p));
+    }
+    products
+}
+
 pub fn generate_sizes(start: u64, end: u64, div_per_oct: u64) -> Vec<usize> {
+    generate_sizes_with(start, end, div_per_oct, &SizeGenOptions::default())
+}
+
+// empty when there's nothing easy to FFT in the range
+pub fn generate_sizes_with(start: u64, end: u64, div_per_oct: u64, options: &SizeGenOptions) -> Vec<usize> {
     let mut candidates = Vec::new();
+    if start == 0 || start > end {
+        return Vec::new();
+    }
 
     let startf = start as f64;
     let endf = end as f64;
     let div_per_oct = div_per_oct as f64;
-    let num_sizes = ((endf.log2() - startf.log2()) * div_per_oct) as usize + 1;
+    let num_sizes = ((endf / startf).log2() * div_per_oct) as usize + 1;
 
-    let primes = &[1, 5, 7, 11];
-    // mandate it is divisible by 4
-    for f2 in 2..=endf.log2().ceil() as u32 {
+    let divisor = options.require_divisible_by.max(1);
+    let products = prime_products(&options.allowed_primes, options.max_primes);
+    for f2 in 0..=endf.log2().ceil() as u32 {
         let f2 = 2u64.pow(f2);
         for f3 in 0..=endf.log(3.).ceil() as u32 {
             let f3 = 3u64.pow(f3);
-            for i in 0..primes.len() {
-                let p1 = primes[i];
-                for j in i..primes.len() {
-                    let p2 = primes[j];
-
-                    let x = f2 * f3 * p1 * p2;
+            for &p in products.iter() {
+                let x = f2 * f3 * p;
 
-                    if x >= start && x <= end {
-                        candidates.push(x);
-                    }
+                if x >= start && x <= end && x % divisor == 0 {
+                    candidates.push(x);
                 }
             }
         }
     }
     candidates.sort();
+    if candidates.is_empty() {
+        return Vec::new();
+    }
     // dbg!(&candidates);
     let candidates_log: Vec<_> = candidates.iter().map(|&x| (x as f64).log2()).collect();
 
@@ -48,6 +89,54 @@ pub fn generate_sizes(start: u64, end: u64, div_per_oct: u64) -> Vec<usize> {
     }).collect()
 }
 
+// grain durations in milliseconds rather than samples
+pub fn generate_sizes_ms(min_ms: f32, max_ms: f32, div_per_oct: u64, sample_rate: f32) -> Vec<usize> {
+    let to_samples = |ms: f32| (ms as f64 * sample_rate as f64 / 1000.).round() as u64;
+
+    generate_sizes(to_samples(min_ms), to_samples(max_ms), div_per_oct)
+}
+
+// 64..8192 samples at 44.1k, scaled so grain durations (and delay) are the same at any sample rate
+pub fn default_sizes(sample_rate: f32) -> Vec<usize> {
+    // keep exactly 7 octaves so every rate gets the same number of grain sizes
+    let start = (64. * sample_rate as f64 / 44100.).round() as u64;
+
+    generate_sizes(start, start * 128, 9)
+}
+
+// samples between frames
+pub fn hop(size: usize, overlap: Overlap) -> usize {
+    size / overlap.factor()
+}
+
+// a full grain has to be buffered, and the last hop of it overlap-added, before it comes out
+pub fn latency(size: usize, overlap: Overlap) -> usize {
+    size + hop(size, overlap)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrainSpec {
+    pub size: usize,
+    pub hop: usize,
+    // when used as the grain size and delay compensation together
+    pub latency_samples: usize,
+    pub duration_ms: f32,
+}
+
+impl GrainSpec {
+    pub fn new(size: usize, overlap: Overlap, sample_rate: f32) -> Self {
+        Self {
+            size,
+            hop: hop(size, overlap),
+            latency_samples: latency(size, overlap),
+            duration_ms: size as f32 * 1000. / sample_rate,
+        }
+    }
+}
+
+pub fn generate_grains(start: u64, end: u64, div_per_oct: u64, overlap: Overlap, sample_rate: f32) -> Vec<GrainSpec> {
+    generate_sizes(start, end, div_per_oct).into_iter().map(|size| GrainSpec::new(size, overlap, sample_rate)).collect()
+}
 
 #[cfg(test)]
 mod tests {
@@ -74,4 +163,43 @@ mod tests {
         // dbg!(_s);
         // panic!("###################")
     }
+
+    #[test]
+    fn size_gen_options() {
+        let radix2 = SizeGenOptions { max_primes: 0, allowed_primes: vec![], require_divisible_by: 8 };
+        let sizes = generate_sizes_with(8, 64, 1, &radix2);
+        assert_eq!(sizes, [8, 16, 32, 64]);
+        let odd = SizeGenOptions { require_divisible_by: 1, ..Default::default() };
+        assert_eq!(generate_sizes_with(49, 50, 1, &odd), [49]);
+
+        // any size goes
+        let any = SizeGenOptions { require_divisible_by: 0, ..Default::default() };
+        assert_eq!(generate_sizes_with(49, 50, 1, &any), [49]);
+    }
+
+    #[test]
+    fn grain_specs() {
+        let grains = generate_grains(64, 8192, 9, Overlap::ThreeQuarters, 48000.);
+        assert_eq!(grains[0], GrainSpec { size: 64, hop: 16, latency_samples: 80, duration_ms: 64. / 48. });
+    }
+
+    #[test]
+    fn sizes_ms() {
+        assert_eq!(generate_sizes_ms(1., 4., 1, 48000.), [48, 96, 192]);
+    }
+
+    #[test]
+    fn default_sizes_scale() {
+        let s44 = default_sizes(44100.);
+        assert_eq!(s44, generate_sizes(64, 8192, 9));
+        for &rate in &[48000., 96000., 192000.] {
+            let s = default_sizes(rate);
+            assert_eq!(s.len(), s44.len());
+            for (&x, &y) in s.iter().zip(&s44) {
+                let ratio = (x as f32 / rate) / (y as f32 / 44100.);
+                // limited by how densely the easy-to-FFT candidates are spaced
+                assert!(ratio.log2().abs() < 1. / 8., "{} {} {}", rate, x, y);
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+use crate::spectral_decay::SpectralDecayError;
+use crate::window::WindowKind;
+
+// the read-only part of the grain setup, shared between channels through an Arc
+// the FFT plans and their scratch space are in GrainPlans
+pub struct GrainTable {
+    window_kind: WindowKind,
+    sizes: Vec<usize>,
+    windows: Vec<Vec<f32>>,
+}
+
+impl GrainTable {
+    pub fn new(grain_sizes: &[usize], window_kind: WindowKind) -> Result<Self, SpectralDecayError> {
+        if grain_sizes.is_empty() {
+            return Err(SpectralDecayError::NoGrainSizes);
+        }
+        if let Some(&n) = grain_sizes.iter().find(|&&n| n == 0 || n % 4 != 0) {
+            return Err(SpectralDecayError::InvalidGrainSize(n));
+        }
+        // allow duplicate grain sizes for even spacing
+        if !grain_sizes.windows(2).all(|n| n[0] <= n[1]) {
+            return Err(SpectralDecayError::UnsortedGrainSizes);
+        }
+
+        Ok(Self {
+            window_kind,
+            sizes: grain_sizes.to_vec(),
+            windows: grain_sizes.iter().map(|&n| window_kind.generate(n)).collect(),
+        })
+    }
+
+    pub fn window_kind(&self) -> WindowKind {
+        self.window_kind
+    }
+
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    pub fn max_size(&self) -> usize {
+        *self.sizes.last().unwrap()
+    }
+
+    pub fn window(&self, index: usize) -> &[f32] {
+        &self.windows[index]
+    }
+}
@@ -0,0 +1,90 @@
+use rustfft::num_complex::Complex;
+
+// median filtering harmonic/percussive separation: harmonics are steady across frames,
+// percussion is smooth across bins. only past frames are used, so there's no extra latency
+const HISTORY_FRAMES: usize = 9;
+const KERNEL_BINS: usize = 9;
+
+fn median(xs: &mut [f32]) -> f32 {
+    xs.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    xs[xs.len() / 2]
+}
+
+pub struct Hpss {
+    // the last HISTORY_FRAMES magnitude spectra, oldest overwritten first
+    history: Vec<Vec<f32>>,
+    next: usize,
+}
+
+impl Hpss {
+    pub fn new(max_bins: usize) -> Self {
+        Self {
+            history: vec![vec![0.; max_bins]; HISTORY_FRAMES],
+            next: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for frame in self.history.iter_mut() {
+            for x in frame.iter_mut() {
+                *x = 0.;
+            }
+        }
+        self.next = 0;
+    }
+
+    // how much of each bin is harmonic, from 0 to 1 (the rest is percussive)
+    pub fn harmonic_mask(&mut self, bins: &[Complex<f32>], mask: &mut [f32]) {
+        let num_bins = bins.len();
+        let frame = &mut self.history[self.next];
+        for (y, x) in frame.iter_mut().zip(bins) {
+            *y = x.norm();
+        }
+        let frame = &self.history[self.next];
+        self.next = (self.next + 1) % HISTORY_FRAMES;
+
+        let mut across_frames = [0.; HISTORY_FRAMES];
+        let mut across_bins = [0.; KERNEL_BINS];
+        for (k, m) in mask[..num_bins].iter_mut().enumerate() {
+            for (w, past) in across_frames.iter_mut().zip(self.history.iter()) {
+                *w = past[k];
+            }
+            let harmonic = median(&mut across_frames);
+
+            // narrower at the edges
+            let start = k.saturating_sub(KERNEL_BINS / 2);
+            let end = (k + KERNEL_BINS / 2 + 1).min(num_bins);
+            let neighbors = &mut across_bins[..end - start];
+            neighbors.copy_from_slice(&frame[start..end]);
+            let percussive = median(neighbors);
+
+            let (h, p) = (harmonic * harmonic, percussive * percussive);
+            *m = if h + p > 0. { h / (h + p) } else { 0.5 };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hpss() {
+        let num_bins = 33;
+        let mut hpss = Hpss::new(num_bins);
+        let mut mask = vec![0.; num_bins];
+
+        // a steady tone in bin 10
+        let mut tone = vec![Complex::new(0., 0.); num_bins];
+        tone[10] = Complex::new(1., 0.);
+        for _ in 0..HISTORY_FRAMES {
+            hpss.harmonic_mask(&tone, &mut mask);
+        }
+        assert!(mask[10] > 0.9);
+
+        // a click has a flat spectrum that wasn't there before
+        let click = vec![Complex::new(0.5, 0.); num_bins];
+        hpss.harmonic_mask(&click, &mut mask);
+        assert!(mask[20] < 0.1);
+    }
+}
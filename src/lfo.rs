@@ -0,0 +1,134 @@
+use crate::spectral_decay::SpectralDecayParameters;
+use crate::rng::{new_rng, seeded_rng};
+use random_fast_rng::{FastRng, Random};
+use std::f32::consts::PI;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    // a new random value every cycle
+    SampleAndHold,
+}
+
+// grain size is only picked once per block, so it can't be modulated per frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LfoTarget {
+    Loss,
+    Fuzz,
+    GlitchFreq,
+    Mix,
+    Blur,
+    // +-12 semitones at full depth
+    Pitch,
+    // +-12 dB per octave at full depth
+    Tilt,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LfoParameters {
+    // in Hz
+    pub rate: f32,
+    // 0..1, off at 0
+    pub depth: f32,
+    pub shape: LfoShape,
+    pub target: LfoTarget,
+}
+
+impl Default for LfoParameters {
+    fn default() -> Self {
+        Self {
+            rate: 1.,
+            depth: 0.,
+            shape: LfoShape::Sine,
+            target: LfoTarget::Loss,
+        }
+    }
+}
+
+pub struct Lfo {
+    // 0..1
+    phase: f32,
+    held: f32,
+    rng: FastRng,
+}
+
+impl Default for Lfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lfo {
+    pub fn new() -> Self {
+        Self {
+            phase: 0.,
+            held: 0.,
+            rng: new_rng(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.phase = 0.;
+        self.held = 0.;
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = seeded_rng(seed);
+    }
+
+    // -1..1 at the current phase, then move forward by seconds
+    pub fn advance(&mut self, shape: LfoShape, rate: f32, seconds: f32) -> f32 {
+        let p = self.phase;
+        let value = match shape {
+            LfoShape::Sine => (2. * PI * p).sin(),
+            LfoShape::Triangle => 1. - 4. * (p - 0.5).abs(),
+            LfoShape::Saw => 2. * p - 1.,
+            LfoShape::Square => if p < 0.5 { 1. } else { -1. },
+            LfoShape::SampleAndHold => self.held,
+        };
+
+        self.phase += rate * seconds;
+        if self.phase >= 1. {
+            self.phase = self.phase.fract();
+            self.held = 2. * (self.rng.gen::<u32>() as f32 / u32::MAX as f32) - 1.;
+        }
+        value
+    }
+
+    // offset the target by value (-1..1) times depth, staying within its range
+    pub fn modulate(params: &mut SpectralDecayParameters, lfo: &LfoParameters, value: f32) {
+        let amount = value * lfo.depth;
+        match lfo.target {
+            LfoTarget::Loss => params.loss = (params.loss + amount).clamp(0., 1.),
+            LfoTarget::Fuzz => params.fuzz = (params.fuzz + amount).clamp(0., 1.),
+            LfoTarget::GlitchFreq => params.glitch_freq = (params.glitch_freq + amount).clamp(0., 1.),
+            LfoTarget::Mix => params.mix = (params.mix + amount).clamp(0., 1.),
+            LfoTarget::Blur => params.blur = (params.blur + amount).clamp(0., 0.99),
+            LfoTarget::Pitch => params.pitch += 12. * amount,
+            LfoTarget::Tilt => params.tilt += 12. * amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lfo() {
+        let mut lfo = Lfo::new();
+        let values: Vec<_> = (0..4).map(|_| lfo.advance(LfoShape::Triangle, 1., 0.25)).collect();
+        assert_eq!(values, vec![-1., 0., 1., 0.]);
+
+        let mut params = SpectralDecayParameters::default();
+        let loss = LfoParameters { depth: 0.5, ..LfoParameters::default() };
+        Lfo::modulate(&mut params, &loss, 1.);
+        assert_eq!(params.loss, 0.5);
+        Lfo::modulate(&mut params, &loss, -1.);
+        Lfo::modulate(&mut params, &loss, -1.);
+        assert_eq!(params.loss, 0.);
+    }
+}
@@ -0,0 +1,63 @@
+// soft knee peak limiter with instant attack, so the output never goes over the ceiling
+const KNEE_DB: f32 = 6.;
+const RELEASE_SECONDS: f32 = 0.05;
+
+#[derive(Default)]
+pub struct Limiter {
+    envelope: f32,
+}
+
+impl Limiter {
+    pub fn new() -> Self {
+        Self { envelope: 0. }
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.;
+    }
+
+    pub fn process(&mut self, buf: &mut [f32], ceiling_db: f32, sample_rate: f32) {
+        let release = (-1. / (RELEASE_SECONDS * sample_rate)).exp();
+
+        for x in buf.iter_mut() {
+            self.envelope = x.abs().max(self.envelope * release);
+            if self.envelope == 0. {
+                continue;
+            }
+
+            // infinite ratio above the knee, quadratic through it
+            let over = 20. * self.envelope.log10() - ceiling_db;
+            let reduction = if over < -KNEE_DB / 2. {
+                0.
+            } else if over > KNEE_DB / 2. {
+                over
+            } else {
+                (over + KNEE_DB / 2.).powi(2) / (2. * KNEE_DB)
+            };
+            *x *= 10f32.powf(-reduction / 20.);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limiter() {
+        let mut limiter = Limiter::new();
+        let mut buf: Vec<_> = (0..4410).map(|x| 4. * (x as f32 * 0.05).sin()).collect();
+        limiter.process(&mut buf, -6., 44100.);
+
+        let ceiling = 10f32.powf(-6. / 20.);
+        assert!(buf.iter().all(|x| x.abs() <= ceiling + 1e-6));
+        assert!(buf.iter().any(|x| x.abs() > ceiling * 0.9));
+
+        // quiet signals are left alone
+        limiter.reset();
+        let mut quiet: Vec<_> = (0..4410).map(|x| 0.1 * (x as f32 * 0.05).sin()).collect();
+        let expected = quiet.clone();
+        limiter.process(&mut quiet, -6., 44100.);
+        assert_eq!(quiet, expected);
+    }
+}
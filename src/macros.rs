@@ -0,0 +1,50 @@
+use crate::spectral_decay::SpectralDecayParameters;
+
+// how far one macro knob moves each of the six main parameters when turned all the way up,
+// as a fraction of the parameter's range, negative moves it down
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MacroWeights {
+    pub fuzz: f32,
+    pub loss: f32,
+    pub glitch_freq: f32,
+    pub glitch_gain: f32,
+    pub grain_select: f32,
+    pub delay_select: f32,
+}
+
+impl MacroWeights {
+    // amount is the macro knob, 0..1
+    pub fn apply(&self, params: &mut SpectralDecayParameters, amount: f32) {
+        let offset = |x: f32, weight: f32| (x + weight * amount).clamp(0., 1.);
+
+        params.fuzz = offset(params.fuzz, self.fuzz);
+        params.loss = offset(params.loss, self.loss);
+        params.glitch_freq = offset(params.glitch_freq, self.glitch_freq);
+        params.grain_select = offset(params.grain_select, self.grain_select);
+        params.delay_select = offset(params.delay_select, self.delay_select);
+        // 1..100, exponential like its knob
+        params.glitch_gain = (params.glitch_gain * 100f32.powf(self.glitch_gain * amount)).clamp(1., 100.);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macro_weights() {
+        let weights = MacroWeights { loss: 0.5, fuzz: -1., glitch_gain: 0.5, ..MacroWeights::default() };
+        let mut params = SpectralDecayParameters::default();
+        params.fuzz = 0.25;
+
+        let mut turned = params;
+        weights.apply(&mut turned, 0.);
+        assert!(turned == params);
+
+        weights.apply(&mut turned, 1.);
+        assert_eq!(turned.loss, 0.5);
+        assert_eq!(turned.fuzz, 0.);
+        assert!((turned.glitch_gain - 10.).abs() < 1e-4);
+        assert_eq!(turned.grain_select, 0.);
+    }
+}
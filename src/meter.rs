@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// peaks fall back and rms averages over about this long
+const PEAK_RELEASE_SECONDS: f32 = 0.3;
+const RMS_SECONDS: f32 = 0.3;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Level {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+// f32 bits, so it can be read from any thread while the audio thread updates it
+#[derive(Default)]
+struct AtomicLevel {
+    peak: AtomicU32,
+    rms: AtomicU32,
+}
+
+impl AtomicLevel {
+    fn load(&self) -> Level {
+        Level {
+            peak: f32::from_bits(self.peak.load(Ordering::Relaxed)),
+            rms: f32::from_bits(self.rms.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn store(&self, level: Level) {
+        self.peak.store(level.peak.to_bits(), Ordering::Relaxed);
+        self.rms.store(level.rms.to_bits(), Ordering::Relaxed);
+    }
+}
+
+// levels before and after processing, as of the end of the last process call
+#[derive(Default)]
+pub struct Meters {
+    input: AtomicLevel,
+    output: AtomicLevel,
+}
+
+impl Meters {
+    pub fn input(&self) -> Level {
+        self.input.load()
+    }
+
+    pub fn output(&self) -> Level {
+        self.output.load()
+    }
+
+    pub(crate) fn store(&self, input: Level, output: Level) {
+        self.input.store(input);
+        self.output.store(output);
+    }
+}
+
+// the audio thread's side of one meter
+#[derive(Default)]
+pub struct Meter {
+    peak: f32,
+    mean_square: f32,
+}
+
+impl Meter {
+    pub fn reset(&mut self) {
+        self.peak = 0.;
+        self.mean_square = 0.;
+    }
+
+    pub fn process(&mut self, buf: &[f32], sample_rate: f32) -> Level {
+        let release = (-1. / (PEAK_RELEASE_SECONDS * sample_rate)).exp();
+        let average = (-1. / (RMS_SECONDS * sample_rate)).exp();
+
+        for x in buf {
+            self.peak = x.abs().max(self.peak * release);
+            self.mean_square = x * x + (self.mean_square - x * x) * average;
+        }
+        Level {
+            peak: self.peak,
+            rms: self.mean_square.sqrt(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meter() {
+        let mut meter = Meter::default();
+        let sine: Vec<_> = (0..44100).map(|x| 0.5 * (x as f32 * 0.05).sin()).collect();
+        let level = meter.process(&sine, 44100.);
+        assert!((level.peak - 0.5).abs() < 1e-3);
+        assert!((level.rms - 0.5 / 2f32.sqrt()).abs() < 0.01);
+
+        // peaks fall off in silence
+        let level = meter.process(&[0.; 44100], 44100.);
+        assert!(level.peak < 0.05);
+
+        let meters = Meters::default();
+        meters.store(level, Level { peak: 1., rms: 0.5 });
+        assert_eq!(meters.input(), level);
+        assert_eq!(meters.output().rms, 0.5);
+    }
+}
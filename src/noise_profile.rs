@@ -0,0 +1,72 @@
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// the average magnitude spectrum of a stretch of noise, what denoising subtracts from every frame
+// unlike a print its levels are absolute, as they come out of the FFT of one grain_size,
+// and get rescaled for noise energy spreading over more or fewer bins at other grain sizes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoiseProfile {
+    magnitudes: Vec<f32>,
+}
+
+impl NoiseProfile {
+    // from magnitudes summed over some frames, reusing the allocation if it's big enough
+    pub(crate) fn set_sum(&mut self, sum: &[f32], frames: usize) {
+        let scale = 1. / frames.max(1) as f32;
+        self.magnitudes.clear();
+        self.magnitudes.extend(sum.iter().map(|&x| x * scale));
+    }
+
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.magnitudes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.magnitudes.is_empty()
+    }
+
+    // linearly interpolated noise magnitude of bin k out of num_bins
+    pub fn level(&self, k: usize, num_bins: usize) -> f32 {
+        let len = self.magnitudes.len();
+        if len < 2 {
+            return self.magnitudes.first().copied().unwrap_or(0.);
+        }
+        let x = k as f32 * (len - 1) as f32 / (num_bins - 1).max(1) as f32;
+        let i = (x as usize).min(len - 2);
+        let t = (x - i as f32).min(1.);
+        let level = self.magnitudes[i] + (self.magnitudes[i + 1] - self.magnitudes[i]) * t;
+        // noise adds up in power, so its bins grow with the square root of the grain size
+        level * ((num_bins - 1) as f32 / (len - 1) as f32).sqrt()
+    }
+}
+
+pub fn save(path: impl AsRef<Path>, profile: &NoiseProfile) -> io::Result<()> {
+    let json = serde_json::to_string(profile)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, json)
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<NoiseProfile> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_level() {
+        let mut profile = NoiseProfile::default();
+        assert_eq!(profile.level(3, 5), 0.);
+
+        profile.set_sum(&[2., 4., 6.], 2);
+        assert_eq!(profile.magnitudes(), &[1., 2., 3.]);
+        assert_eq!(profile.level(1, 3), 2.);
+        // four times the bins, twice the level
+        assert_eq!(profile.level(4, 9), 4.);
+        assert_eq!(profile.level(6, 9), 5.);
+    }
+}
@@ -0,0 +1,52 @@
+use rustfft::num_complex::Complex;
+
+// spectral flux: how much the spectrum grew since the last frame, relative to how loud it is now
+// 0 for steady material, up to 1 on onsets
+pub struct SpectralFlux {
+    prev: Vec<f32>,
+}
+
+impl SpectralFlux {
+    pub fn new(max_bins: usize) -> Self {
+        Self {
+            prev: vec![0.; max_bins],
+        }
+    }
+
+    // the next frame is compared against silence
+    pub fn clear(&mut self) {
+        for x in self.prev.iter_mut() {
+            *x = 0.;
+        }
+    }
+
+    pub fn process(&mut self, bins: &[Complex<f32>]) -> f32 {
+        let mut rise = 0.;
+        let mut total = 0.;
+        for (x, prev) in bins.iter().zip(self.prev.iter_mut()) {
+            let r = x.norm();
+            rise += (r - *prev).max(0.);
+            total += r;
+            *prev = r;
+        }
+
+        if total > 0. { rise / total } else { 0. }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spectral_flux() {
+        let mut flux = SpectralFlux::new(4);
+        let steady = [Complex::new(1., 0.); 4];
+
+        assert_eq!(flux.process(&steady), 1.);
+        assert_eq!(flux.process(&steady), 0.);
+
+        let louder = [Complex::new(0., 2.); 4];
+        assert_eq!(flux.process(&louder), 0.5);
+    }
+}
@@ -0,0 +1,206 @@
+use rustfft::num_complex::Complex;
+use std::f32::consts::PI;
+
+// sinusoidal modeling: the peaks of every frame are matched up with the last frame's into partials,
+// which are resynthesized on their own, each with its own phase, frequency and lifetime
+// peaks are assumed to have the shape of a hann window's main lobe, which is close enough for the others
+pub const MAX_PARTIALS: usize = 64;
+
+// quieter peaks than this below the loudest one are noise
+const PEAK_FLOOR_DB: f32 = -60.;
+
+// furthest a partial can move between frames and still be the same one, in bins
+const MATCH_BINS: f32 = 1.5;
+
+// the hann window's spectrum at d bins from a sinusoid, relative to the bin it's on, over its main lobe
+fn hann_kernel(d: f32) -> f32 {
+    let d = d.abs();
+    if d < 1e-4 {
+        1.
+    } else if (d - 1.).abs() < 1e-4 {
+        0.5
+    } else if d >= 2. {
+        0.
+    } else {
+        (PI * d).sin() / (PI * d) / (1. - d * d)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Partial {
+    // in bins, as measured
+    freq: f32,
+    amp: f32,
+    // resynthesized frequency and phase, at the start of the grain
+    out_freq: f32,
+    phase: f32,
+    // frequency ratio it was born with, and how fast it decays relative to the others
+    detune: f32,
+    rate: f32,
+    // frames since it was born
+    age: i32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Peak {
+    freq: f32,
+    amp: f32,
+    // of the sinusoid at the start of the grain
+    phase: f32,
+}
+
+pub struct PartialTracker {
+    partials: Vec<Partial>,
+    next: Vec<Partial>,
+    // which of partials were continued this frame
+    taken: Vec<bool>,
+    peaks: Vec<Peak>,
+}
+
+impl PartialTracker {
+    pub fn new(max_fft_size: usize) -> Self {
+        Self {
+            partials: Vec::with_capacity(MAX_PARTIALS),
+            next: Vec::with_capacity(MAX_PARTIALS),
+            taken: Vec::with_capacity(MAX_PARTIALS),
+            peaks: Vec::with_capacity(max_fft_size / 2 + 1),
+        }
+    }
+
+    // every partial ends, and the next frame's are all new
+    pub fn clear(&mut self) {
+        self.partials.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.partials.len()
+    }
+
+    // up to max of the loudest peaks, continuing the nearest partial to each, loudest first
+    // new partials get detuned by up to detune cents either way
+    pub fn track(&mut self, bins: &[Complex<f32>], max: usize, detune: f32, rand: &mut impl FnMut() -> f32) {
+        let max = max.min(MAX_PARTIALS);
+        let max_amp = bins.iter().fold(0., |max: f32, x| x.norm().max(max));
+        let floor = max_amp * 10f32.powf(PEAK_FLOOR_DB / 20.);
+
+        self.peaks.clear();
+        for k in 1..bins.len().saturating_sub(1) {
+            let (a, b, c) = (bins[k - 1].norm(), bins[k].norm(), bins[k + 1].norm());
+            if b <= floor || b <= a || b < c {
+                continue;
+            }
+            // parabolic interpolation of the log magnitudes around the peak
+            let (la, lb, lc) = (a.max(1e-20).ln(), b.ln(), c.max(1e-20).ln());
+            let curvature = la - 2. * lb + lc;
+            let offset = if curvature < 0. { (0.5 * (la - lc) / curvature).clamp(-0.5, 0.5) } else { 0. };
+            self.peaks.push(Peak {
+                freq: k as f32 + offset,
+                amp: b / hann_kernel(offset),
+                phase: bins[k].arg() - PI * offset,
+            });
+        }
+        self.peaks.sort_unstable_by(|a, b| b.amp.partial_cmp(&a.amp).unwrap());
+        self.peaks.truncate(max);
+
+        self.taken.clear();
+        self.taken.resize(self.partials.len(), false);
+        self.next.clear();
+        for peak in self.peaks.iter() {
+            let nearest = self.partials.iter().enumerate()
+                .filter(|&(i, p)| !self.taken[i] && (p.freq - peak.freq).abs() < MATCH_BINS)
+                .min_by(|(_, a), (_, b)| (a.freq - peak.freq).abs().partial_cmp(&(b.freq - peak.freq).abs()).unwrap())
+                .map(|(i, _)| i);
+
+            self.next.push(match nearest {
+                Some(i) => {
+                    self.taken[i] = true;
+                    Partial { freq: peak.freq, amp: peak.amp, age: self.partials[i].age + 1, ..self.partials[i] }
+                }
+                None => Partial {
+                    freq: peak.freq,
+                    amp: peak.amp,
+                    // starts out where the input is, so nothing changes until it's moved
+                    out_freq: peak.freq,
+                    phase: peak.phase,
+                    detune: 2f32.powf(detune * (2. * rand() - 1.) / 1200.),
+                    rate: 0.5 + rand(),
+                    age: 0,
+                }
+            });
+        }
+        std::mem::swap(&mut self.partials, &mut self.next);
+    }
+
+    // bins get only the partials, frequencies scaled by ratio, each losing decay (times its own rate) of its level every frame of its life
+    // phases run on from the last frame at the partial's frequency, so it stays one sinusoid however it's moved
+    pub fn synthesize(&mut self, bins: &mut [Complex<f32>], fft_size: usize, hop: usize, ratio: f32, decay: f32) {
+        for x in bins.iter_mut() {
+            *x = Complex::new(0., 0.);
+        }
+        let advance = 2. * PI * hop as f32 / fft_size as f32;
+        let last = bins.len() - 1;
+
+        for p in self.partials.iter_mut() {
+            let out_freq = p.freq * ratio * p.detune;
+            if p.age > 0 {
+                p.phase = (p.phase + (p.out_freq + out_freq) / 2. * advance) % (2. * PI);
+            }
+            p.out_freq = out_freq;
+
+            let amp = p.amp * (1. - decay * p.rate).max(0.).powi(p.age);
+            let center = out_freq.round() as isize;
+            for k in center - 2..=center + 2 {
+                if k < 0 || k as usize > last {
+                    continue;
+                }
+                let d = out_freq - k as f32;
+                let r = amp * hann_kernel(d);
+                if r > 0. {
+                    bins[k as usize] += Complex::from_polar(r, p.phase + PI * d);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use realfft::RealFftPlanner;
+
+    // a windowed cosine at freq bins, the way SpectralDecay analyzes it
+    fn spectrum(n: usize, freq: f32, phase: f32) -> Vec<Complex<f32>> {
+        let window = crate::window::WindowKind::Hann.generate(n);
+        let mut time: Vec<_> = (0..n).map(|i| 2. * window[i] * (2. * PI * freq * i as f32 / n as f32 + phase).cos()).collect();
+        let mut bins = vec![Complex::new(0., 0.); n / 2 + 1];
+        RealFftPlanner::new().plan_fft_forward(n).process(&mut time, &mut bins).unwrap();
+        bins
+    }
+
+    #[test]
+    fn track_sine() {
+        let n = 64;
+        let mut rand = || 0.5;
+        let mut tracker = PartialTracker::new(n);
+
+        let bins = spectrum(n, 10.3, 1.);
+        tracker.track(&bins, 4, 0., &mut rand);
+        assert_eq!(tracker.len(), 1);
+        assert!((tracker.partials[0].freq - 10.3).abs() < 0.05);
+
+        // resynthesized as it was, near the peak
+        let mut out = bins.clone();
+        tracker.synthesize(&mut out, n, n / 4, 1., 0.);
+        for k in 9..=11 {
+            assert!((out[k] - bins[k]).norm() < 0.15 * bins[10].norm(), "{} {} {}", k, out[k], bins[k]);
+        }
+
+        // the next frame continues it, an octave up
+        tracker.track(&spectrum(n, 10.4, 2.), 4, 0., &mut rand);
+        assert_eq!(tracker.len(), 1);
+        assert_eq!(tracker.partials[0].age, 1);
+        tracker.synthesize(&mut out, n, n / 4, 2., 0.);
+        let peak = out.iter().enumerate().max_by(|(_, a), (_, b)| a.norm().partial_cmp(&b.norm()).unwrap()).unwrap().0;
+        assert_eq!(peak, 21);
+    }
+}
@@ -0,0 +1,158 @@
+use rustfft::num_complex::Complex;
+use std::f32::consts::PI;
+
+// keeps the phases of each bin coherent across hops so bins can be moved to other frequencies
+// see "Pitch Shifting Using The Fourier Transform" by Stephan Bernsee
+
+fn wrap_phase(x: f32) -> f32 {
+    x - 2. * PI * (x / (2. * PI)).round()
+}
+
+pub struct PhaseVocoder {
+    last_phase: Vec<f32>,
+    sum_phase: Vec<f32>,
+    mag: Vec<f32>,
+    freq: Vec<f32>,
+}
+
+impl PhaseVocoder {
+    pub fn new(max_bins: usize) -> Self {
+        Self {
+            last_phase: vec![0.; max_bins],
+            sum_phase: vec![0.; max_bins],
+            mag: vec![0.; max_bins],
+            freq: vec![0.; max_bins],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for x in self.last_phase.iter_mut().chain(self.sum_phase.iter_mut()) {
+            *x = 0.;
+        }
+    }
+
+    // scale the frequency of every bin by ratio
+    pub fn pitch_shift(&mut self, bins: &mut [Complex<f32>], grain_size: usize, hop: usize, ratio: f32) {
+        let num_bins = bins.len();
+        let last_phase = &mut self.last_phase[..num_bins];
+        let sum_phase = &mut self.sum_phase[..num_bins];
+        let mag = &mut self.mag[..num_bins];
+        let freq = &mut self.freq[..num_bins];
+
+        // phase advance per hop of a sinusoid centered on bin 1
+        let expected = 2. * PI * hop as f32 / grain_size as f32;
+
+        for x in mag.iter_mut().chain(freq.iter_mut()) {
+            *x = 0.;
+        }
+
+        // analysis: estimate the true frequency of each bin (in bins) and move it
+        for (k, x) in bins.iter().enumerate() {
+            let (r, theta) = x.to_polar();
+            let delta = wrap_phase(theta - last_phase[k] - k as f32 * expected);
+            last_phase[k] = theta;
+
+            let target = (k as f32 * ratio).round() as usize;
+            if target < num_bins {
+                mag[target] += r;
+                freq[target] = (k as f32 + delta / expected) * ratio;
+            }
+        }
+
+        // synthesis: accumulate phase at the shifted frequencies
+        for (k, x) in bins.iter_mut().enumerate() {
+            sum_phase[k] = wrap_phase(sum_phase[k] + freq[k] * expected);
+            *x = Complex::from_polar(mag[k], sum_phase[k]);
+        }
+    }
+
+    // frames analyzed analysis_hop samples apart come out synthesis_hop samples apart, time stretching by the ratio
+    // the analysis can also move backwards, e.g. for a grain read from further back than the last one
+    // restart takes the phases as they are, for the first frame or one that doesn't follow on from the last
+    pub fn stretch(&mut self, bins: &mut [Complex<f32>], fft_size: usize, analysis_hop: isize, synthesis_hop: usize, restart: bool) {
+        let num_bins = bins.len();
+        let last_phase = &mut self.last_phase[..num_bins];
+        let sum_phase = &mut self.sum_phase[..num_bins];
+        // true frequency of each bin in radians per sample, kept from the last frame when the analysis didn't move
+        let freq = &mut self.freq[..num_bins];
+        let bin_freq = 2. * PI / fft_size as f32;
+
+        for (k, x) in bins.iter_mut().enumerate() {
+            let (r, theta) = x.to_polar();
+            if restart {
+                sum_phase[k] = theta;
+                freq[k] = k as f32 * bin_freq;
+            } else {
+                if analysis_hop != 0 {
+                    let delta = wrap_phase(theta - last_phase[k] - k as f32 * bin_freq * analysis_hop as f32);
+                    freq[k] = k as f32 * bin_freq + delta / analysis_hop as f32;
+                }
+                sum_phase[k] = wrap_phase(sum_phase[k] + freq[k] * synthesis_hop as f32);
+            }
+            last_phase[k] = theta;
+            *x = Complex::from_polar(r, sum_phase[k]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustfft::num_traits::Zero;
+
+    #[test]
+    fn octave_up() {
+        let n = 64;
+        let hop = n / 4;
+        let mut pv = PhaseVocoder::new(n / 2 + 1);
+        let mut bins = vec![Complex::zero(); n / 2 + 1];
+
+        // a steady sinusoid on bin 5 advances its phase by 5 * 2pi * hop / n every frame
+        for frame in 0..8 {
+            bins[5] = Complex::from_polar(1., 5. * 2. * PI * (frame * hop) as f32 / n as f32);
+            for x in bins.iter_mut().skip(6) {
+                *x = Complex::zero();
+            }
+            pv.pitch_shift(&mut bins, n, hop, 2.);
+
+            let (peak, _) = bins.iter().enumerate().max_by(|(_, x), (_, y)| {
+                x.norm().partial_cmp(&y.norm()).unwrap()
+            }).unwrap();
+            assert_eq!(peak, 10);
+        }
+    }
+
+    #[test]
+    fn stretch_twice() {
+        let n = 64;
+        let hop = n / 4;
+        let mut pv = PhaseVocoder::new(n / 2 + 1);
+        let mut bins = vec![Complex::zero(); n / 2 + 1];
+
+        // read every half hop, bin 5 advances by half as much as it should come out advancing
+        for frame in 0..8 {
+            let phase = |t: usize| 5. * 2. * PI * t as f32 / n as f32;
+            bins[5] = Complex::from_polar(1., phase(frame * hop / 2));
+            pv.stretch(&mut bins, n, (hop / 2) as isize, hop, frame == 0);
+            assert!((wrap_phase(bins[5].arg() - phase(frame * hop))).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn stretch_backwards() {
+        let n = 64;
+        let hop = n / 4;
+        let mut pv = PhaseVocoder::new(n / 2 + 1);
+        let mut bins = vec![Complex::zero(); n / 2 + 1];
+
+        // read back and forth, a sinusoid between bins 4 and 5 still comes out advancing by a hop every frame
+        let freq = 4.5 * 2. * PI / n as f32;
+        let reads = [8, 4, 12, 10, 20, 2];
+        for (frame, &read) in reads.iter().enumerate() {
+            bins[4] = Complex::from_polar(1., freq * read as f32);
+            let analysis_hop = if frame == 0 { 0 } else { read as isize - reads[frame - 1] as isize };
+            pv.stretch(&mut bins, n, analysis_hop, hop, frame == 0);
+            assert!((wrap_phase(bins[4].arg() - freq * (reads[0] + frame * hop) as f32)).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,1386 @@
+// the vst plugin, its parameters and presets, only built with the plugin feature
+
+pub mod presets;
+#[cfg(feature = "editor")]
+mod editor;
+
+use crate::{
+    fft_sizes,
+    ChannelGroup,
+    ChannelIo,
+    Component,
+    GrainTable,
+    LfoParameters,
+    LfoShape,
+    LfoTarget,
+    MacroWeights,
+    Overlap,
+    PhaseMode,
+    RandomizeConstraints,
+    SpectralDecay,
+    SpectralDecayParameters,
+    WindowKind
+};
+use crate::decay_processor::LOSS_DB_RANGE;
+use random_fast_rng::{FastRng, Random};
+use serde::{Serialize, Deserialize};
+use std::ops::Range;
+use std::sync::Arc;
+
+use baseplug::{
+    ProcessContext,
+    Plugin,
+    MidiReceiver,
+};
+#[cfg(feature = "editor")]
+use baseplug::{Model, Param, PluginUI};
+#[cfg(feature = "editor")]
+use raw_window_handle::HasRawWindowHandle;
+
+baseplug::model! {
+    // missing fields (e.g. presets saved by an older version) take their default
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct SpectralModel {
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Fuzz")]
+        #[unsmoothed]
+        fuzz: f32,
+
+        // how much less fuzz the lows get than the highs, keeping the bass solid
+        #[model(min = 0.0, max = 4.0)]
+        #[parameter(name = "Fuzz slope")]
+        #[unsmoothed]
+        fuzz_slope: f32,
+
+        // threshold below the loudest bin (or full scale with loss dB), from -96dB, where it's off, up to 0dB
+        // the bottom is loss_floor() rounded down, so it stays off
+        #[model(min = 0.0000158, max = 1.0)]
+        #[parameter(name = "Loss", unit = "Decibels",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        loss: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Glitch frequency")]
+        #[unsmoothed]
+        glitch_freq: f32,
+
+        #[model(min = 1.0, max = 100.0)]
+        #[parameter(name = "Glitch gain", unit = "Decibels",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        glitch_gain: f32,
+
+        // negative puts more glitches in the lows, positive in the highs
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Glitch tilt")]
+        #[unsmoothed]
+        glitch_tilt: f32,
+
+        // above 0 glitched bins stick around for up to this many frames
+        #[model(min = 0.0, max = 64.0)]
+        #[parameter(name = "Glitch hold")]
+        #[unsmoothed]
+        glitch_hold: f32,
+
+        // above 0 glitched bins fade out over about this many frames
+        #[model(min = 0.0, max = 64.0)]
+        #[parameter(name = "Glitch decay")]
+        #[unsmoothed]
+        glitch_decay: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Grain size")]
+        #[unsmoothed]
+        grain_select: f32,
+
+        // modulating "Grain size" changes the amount of delay, which causes time stretching
+        // to avoid time stretching, set "Delay compensation" to the max value of "Grain size"
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Delay compensation")]
+        #[unsmoothed]
+        delay_select: f32,
+
+        // below the grain size, the grain is resynthesized through a shorter window, smearing less over time
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Synthesis size")]
+        #[unsmoothed]
+        synthesis_select: f32,
+
+        // latch the current spectrum and keep resynthesizing it while above 0.5
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Freeze")]
+        #[unsmoothed]
+        freeze: f32,
+
+        // while frozen, how fast the spectrum decays and drifts apart
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Melt")]
+        #[unsmoothed]
+        melt: f32,
+
+        // above 0.5 every bin holds the loudest it's been, building up a drone
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Accumulate")]
+        #[unsmoothed]
+        accumulate: f32,
+
+        // going above 0.5 clears the accumulated spectrum
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Accumulate clear")]
+        #[unsmoothed]
+        accumulate_clear: f32,
+
+        // how many of the loudest partials are tracked and resynthesized, everything else is dropped, off at 0
+        #[model(min = 0.0, max = 64.0)]
+        #[parameter(name = "Partials")]
+        #[unsmoothed]
+        partials: f32,
+
+        // new partials are detuned by up to this many cents either way
+        #[model(min = 0.0, max = 100.0)]
+        #[parameter(name = "Partial detune")]
+        #[unsmoothed]
+        partial_detune: f32,
+
+        // how fast each partial fades over its lifetime
+        #[model(min = 0.0, max = 0.1)]
+        #[parameter(name = "Partial decay")]
+        #[unsmoothed]
+        partial_decay: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Mix")]
+        #[unsmoothed]
+        mix: f32,
+
+        #[model(min = -24.0, max = 24.0)]
+        #[parameter(name = "Pitch")]
+        #[unsmoothed]
+        pitch: f32,
+
+        // stretch or squash the frequency axis, moving partials apart inharmonically
+        #[model(min = 0.5, max = 2.0)]
+        #[parameter(name = "Spread")]
+        #[unsmoothed]
+        spread: f32,
+
+        // shift the spectral envelope independently of pitch
+        #[model(min = -24.0, max = 24.0)]
+        #[parameter(name = "Formant")]
+        #[unsmoothed]
+        formant: f32,
+
+        // negative keeps only the partials, positive keeps only the envelope
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Lifter")]
+        #[unsmoothed]
+        lifter: f32,
+
+        // 50%, 75% or 87.5%, more overlap is smoother but costs more CPU
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Overlap")]
+        #[unsmoothed]
+        overlap: f32,
+
+        // in frames, smooths the loss gate per bin
+        #[model(min = 0.0, max = 32.0)]
+        #[parameter(name = "Gate attack")]
+        #[unsmoothed]
+        gate_attack: f32,
+
+        #[model(min = 0.0, max = 32.0)]
+        #[parameter(name = "Gate release")]
+        #[unsmoothed]
+        gate_release: f32,
+
+        // in frames, for the highest bin
+        #[model(min = 0.0, max = 64.0)]
+        #[parameter(name = "Spectral delay")]
+        #[unsmoothed]
+        spectral_delay: f32,
+
+        #[model(min = 0.0, max = 0.95)]
+        #[parameter(name = "Spectral delay feedback")]
+        #[unsmoothed]
+        spectral_delay_feedback: f32,
+
+        // smears each bin's magnitude over time
+        #[model(min = 0.0, max = 0.99)]
+        #[parameter(name = "Blur")]
+        #[unsmoothed]
+        blur: f32,
+
+        // how long each bin's peak takes to die away, like a spectral reverb tail
+        #[model(min = 0.0, max = 0.99)]
+        #[parameter(name = "Decay")]
+        #[unsmoothed]
+        decay: f32,
+
+        // below 0.5 processes left/right, above processes mid/side (of the first two channels)
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Stereo mode")]
+        #[unsmoothed]
+        stereo_mode: f32,
+
+        // above 0.5 all channels draw the same random numbers, so fuzz and glitches stay centered
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Stereo link")]
+        #[unsmoothed]
+        stereo_link: f32,
+
+        // when the buffers and per-bin state are cleared: below 1/3 never, below 2/3 when playback stops,
+        // above that also when it starts and whenever it loops back
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Transport reset")]
+        #[unsmoothed]
+        transport_reset: f32,
+
+        // subtract the sidechain's (inputs 3 and 4) spectrum from the input's
+        #[model(min = 0.0, max = 4.0)]
+        #[parameter(name = "Sidechain duck")]
+        #[unsmoothed]
+        duck: f32,
+
+        // blend the input's magnitude spectrum towards the sidechain's, keeping the input's phases
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Sidechain morph")]
+        #[unsmoothed]
+        morph: f32,
+
+        // shape the input with the sidechain's spectral envelope, like a channel vocoder
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Sidechain vocode")]
+        #[unsmoothed]
+        vocode: f32,
+
+        // while above 0.5 the input's average spectrum is captured, on the way back down it becomes the print
+        // the print itself isn't saved with the session, see SpectralPlugin::process
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Print capture")]
+        #[unsmoothed]
+        print_capture: f32,
+
+        // how much the output is filtered into the shape of the captured print
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Print")]
+        #[unsmoothed]
+        print: f32,
+
+        // while above 0.5 the input is learned as the noise profile, on the way back down denoising starts using it
+        // like prints, the profile isn't saved with the session yet
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Denoise learn")]
+        #[unsmoothed]
+        denoise_learn: f32,
+
+        // how many times the learned noise is subtracted
+        #[model(min = 0.0, max = 4.0)]
+        #[parameter(name = "Denoise")]
+        #[unsmoothed]
+        denoise: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Denoise floor")]
+        #[unsmoothed]
+        denoise_floor: f32,
+
+        // off, 1/4, 1/8, 1/8T, 1/16, 1/16T, 1/32
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Glitch sync")]
+        #[unsmoothed]
+        glitch_sync: f32,
+
+        // keep (plus fuzz), robotize or whisperize
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Phase mode")]
+        #[unsmoothed]
+        phase_mode: f32,
+
+        // both, only harmonic or only percussive, the other passes through untouched
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Process component")]
+        #[unsmoothed]
+        component: f32,
+
+        // relative to each frame's loudest bin
+        #[model(min = -60.0, max = 0.0)]
+        #[parameter(name = "Compressor threshold", unit = "Decibels")]
+        #[unsmoothed]
+        compress_threshold: f32,
+
+        #[model(min = 1.0, max = 20.0)]
+        #[parameter(name = "Compressor ratio")]
+        #[unsmoothed]
+        compress_ratio: f32,
+
+        // in frames
+        #[model(min = 0.0, max = 32.0)]
+        #[parameter(name = "Compressor attack")]
+        #[unsmoothed]
+        compress_attack: f32,
+
+        #[model(min = 0.0, max = 32.0)]
+        #[parameter(name = "Compressor release")]
+        #[unsmoothed]
+        compress_release: f32,
+
+        #[model(min = -12.0, max = 12.0)]
+        #[parameter(name = "Tilt", unit = "Decibels")]
+        #[unsmoothed]
+        tilt: f32,
+
+        // the frequency tilt leaves alone
+        #[model(min = 20.0, max = 20000.0)]
+        #[parameter(name = "Tilt center", gradient = "Exponential")]
+        #[unsmoothed]
+        tilt_center: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Scramble")]
+        #[unsmoothed]
+        scramble: f32,
+
+        // in bins, how far a scrambled bin can move
+        #[model(min = 1.0, max = 64.0)]
+        #[parameter(name = "Scramble range")]
+        #[unsmoothed]
+        scramble_range: f32,
+
+        // levels bin magnitudes are quantized to, 0 is off
+        #[model(min = 0.0, max = 64.0)]
+        #[parameter(name = "Crush")]
+        #[unsmoothed]
+        crush: f32,
+
+        // above 0.5 spaces the levels in dB
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Crush dB scale")]
+        #[unsmoothed]
+        crush_db: f32,
+
+        // glitch, loss, fuzz and crush only apply to frequencies in between
+        #[model(min = 0.0, max = 20000.0)]
+        #[parameter(name = "Low cut", gradient = "Exponential")]
+        #[unsmoothed]
+        low_cut: f32,
+
+        // the max value doesn't cut anything
+        #[model(min = 20.0, max = 20000.0)]
+        #[parameter(name = "High cut", gradient = "Exponential")]
+        #[unsmoothed]
+        high_cut: f32,
+
+        // spare transients from loss and hit sustains harder
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Adaptive loss")]
+        #[unsmoothed]
+        adaptive_loss: f32,
+
+        // above 0.5 loss throws away the loud bins and keeps the residue
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Loss invert")]
+        #[unsmoothed]
+        loss_invert: f32,
+
+        // above 0.5 the loss threshold is a fixed level in dBFS instead of following the loudest bin
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Loss dB")]
+        #[unsmoothed]
+        loss_db: f32,
+
+        // let onsets through unprocessed
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Preserve transients")]
+        #[unsmoothed]
+        preserve_transients: f32,
+
+        // above 0.5 limits the output to "Ceiling"
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Limiter")]
+        #[unsmoothed]
+        limiter: f32,
+
+        #[model(min = -24.0, max = 0.0)]
+        #[parameter(name = "Ceiling", unit = "Decibels")]
+        #[unsmoothed]
+        ceiling: f32,
+
+        // above 0.5 silences DC and fades in everything under 20 Hz
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "DC block")]
+        #[unsmoothed]
+        dc_block: f32,
+
+        // above 0.5 grains that peak over full scale are turned down by their peak, like a limiter that changes every frame
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Auto gain")]
+        #[unsmoothed]
+        auto_gain: f32,
+
+        // drag: above 1 the output slows down and falls behind the input, up to a few of the longest grains,
+        // then jumps back to it, and below 1 it speeds up again until it's caught up
+        #[model(min = 0.25, max = 4.0)]
+        #[parameter(name = "Stretch")]
+        #[unsmoothed]
+        stretch: f32,
+
+        // reads each grain from up to 4 hops back at random
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Jitter")]
+        #[unsmoothed]
+        jitter: f32,
+
+        // chance of each grain playing backwards
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Reverse")]
+        #[unsmoothed]
+        reverse: f32,
+
+        // above 0.5 fades to the dry signal, delayed so it stays in time with the processed one
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Bypass")]
+        #[unsmoothed]
+        bypass: f32,
+
+        #[model(min = 0.01, max = 20.0)]
+        #[parameter(name = "LFO 1 rate", unit = "Hertz",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        lfo1_rate: f32,
+
+        // off, or follow the host tempo: 4 bars, 2 bars, 1 bar, 1/2, 1/4, 1/8, 1/16
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 1 sync")]
+        #[unsmoothed]
+        lfo1_sync: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 1 depth")]
+        #[unsmoothed]
+        lfo1_depth: f32,
+
+        // sine, triangle, saw, square or sample and hold
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 1 shape")]
+        #[unsmoothed]
+        lfo1_shape: f32,
+
+        // loss, fuzz, glitch frequency, mix, blur, pitch or tilt
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 1 target")]
+        #[unsmoothed]
+        lfo1_target: f32,
+
+        #[model(min = 0.01, max = 20.0)]
+        #[parameter(name = "LFO 2 rate", unit = "Hertz",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        lfo2_rate: f32,
+
+        // off, or follow the host tempo: 4 bars, 2 bars, 1 bar, 1/2, 1/4, 1/8, 1/16
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 2 sync")]
+        #[unsmoothed]
+        lfo2_sync: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 2 depth")]
+        #[unsmoothed]
+        lfo2_depth: f32,
+
+        // sine, triangle, saw, square or sample and hold
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 2 shape")]
+        #[unsmoothed]
+        lfo2_shape: f32,
+
+        // loss, fuzz, glitch frequency, mix, blur, pitch or tilt
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "LFO 2 target")]
+        #[unsmoothed]
+        lfo2_target: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Macro 1")]
+        #[unsmoothed]
+        macro1: f32,
+
+        // how far "Macro 1" turns each of the main parameters, as a fraction of its range
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 1 fuzz")]
+        #[unsmoothed]
+        macro1_fuzz: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 1 loss")]
+        #[unsmoothed]
+        macro1_loss: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 1 glitch frequency")]
+        #[unsmoothed]
+        macro1_glitch_freq: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 1 glitch gain")]
+        #[unsmoothed]
+        macro1_glitch_gain: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 1 grain size")]
+        #[unsmoothed]
+        macro1_grain_select: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 1 delay compensation")]
+        #[unsmoothed]
+        macro1_delay_select: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Macro 2")]
+        #[unsmoothed]
+        macro2: f32,
+
+        // how far "Macro 2" turns each of the main parameters, as a fraction of its range
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 2 fuzz")]
+        #[unsmoothed]
+        macro2_fuzz: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 2 loss")]
+        #[unsmoothed]
+        macro2_loss: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 2 glitch frequency")]
+        #[unsmoothed]
+        macro2_glitch_freq: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 2 glitch gain")]
+        #[unsmoothed]
+        macro2_glitch_gain: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 2 grain size")]
+        #[unsmoothed]
+        macro2_grain_select: f32,
+
+        #[model(min = -1.0, max = 1.0)]
+        #[parameter(name = "Macro 2 delay compensation")]
+        #[unsmoothed]
+        macro2_delay_select: f32,
+
+        // rolls new fuzz, loss and glitch settings when crossing 0.5 upwards, as does midi CC 80
+        // they stay until one of those knobs is moved
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Dice")]
+        #[unsmoothed]
+        dice: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Dice fuzz min")]
+        #[unsmoothed]
+        dice_fuzz_min: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Dice fuzz max")]
+        #[unsmoothed]
+        dice_fuzz_max: f32,
+
+        // rolled on the same taper as the "Loss" knob
+        #[model(min = 0.0000158, max = 1.0)]
+        #[parameter(name = "Dice loss min", unit = "Decibels",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        dice_loss_min: f32,
+
+        #[model(min = 0.0000158, max = 1.0)]
+        #[parameter(name = "Dice loss max", unit = "Decibels",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        dice_loss_max: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Dice glitch frequency min")]
+        #[unsmoothed]
+        dice_glitch_freq_min: f32,
+
+        #[model(min = 0.0, max = 1.0)]
+        #[parameter(name = "Dice glitch frequency max")]
+        #[unsmoothed]
+        dice_glitch_freq_max: f32,
+
+        #[model(min = 1.0, max = 100.0)]
+        #[parameter(name = "Dice glitch gain min", unit = "Decibels",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        dice_glitch_gain_min: f32,
+
+        #[model(min = 1.0, max = 100.0)]
+        #[parameter(name = "Dice glitch gain max", unit = "Decibels",
+            gradient = "Exponential")]
+        #[unsmoothed]
+        dice_glitch_gain_max: f32,
+    }
+}
+
+// the bottom of the "Loss" knob, as far down as loss_db reaches
+fn loss_floor() -> f32 {
+    10f32.powf(-LOSS_DB_RANGE / 20.)
+}
+
+// the "Loss" knob's level as SpectralDecayParameters::loss, which is linear unless loss_db, off at the bottom
+fn loss_param(level: f32, loss_db: bool) -> f32 {
+    if level <= loss_floor() {
+        0.
+    } else if loss_db {
+        1. + 20. * level.log10() / LOSS_DB_RANGE
+    } else {
+        level
+    }
+}
+
+// note length in beats of the "Glitch sync" setting
+fn glitch_division(select: f32) -> Option<f64> {
+    const DIVISIONS: &[Option<f64>] = &[None, Some(1.), Some(1. / 2.), Some(1. / 3.), Some(1. / 4.), Some(1. / 6.), Some(1. / 8.)];
+
+    let index = ((select * DIVISIONS.len() as f32) as usize).min(DIVISIONS.len() - 1);
+    DIVISIONS[index]
+}
+
+// cycle length in beats of an "LFO sync" setting
+fn lfo_division(select: f32) -> Option<f64> {
+    const DIVISIONS: &[Option<f64>] = &[None, Some(16.), Some(8.), Some(4.), Some(2.), Some(1.), Some(1. / 2.), Some(1. / 4.)];
+
+    let index = ((select * DIVISIONS.len() as f32) as usize).min(DIVISIONS.len() - 1);
+    DIVISIONS[index]
+}
+
+fn lfo_params(rate: f32, depth: f32, shape: f32, target: f32) -> LfoParameters {
+    const SHAPES: &[LfoShape] = &[LfoShape::Sine, LfoShape::Triangle, LfoShape::Saw, LfoShape::Square, LfoShape::SampleAndHold];
+    const TARGETS: &[LfoTarget] = &[
+        LfoTarget::Loss, LfoTarget::Fuzz, LfoTarget::GlitchFreq, LfoTarget::Mix, LfoTarget::Blur, LfoTarget::Pitch, LfoTarget::Tilt,
+    ];
+    let pick = |x: f32, len: usize| ((x * len as f32) as usize).min(len - 1);
+
+    LfoParameters {
+        rate,
+        depth,
+        shape: SHAPES[pick(shape, SHAPES.len())],
+        target: TARGETS[pick(target, TARGETS.len())],
+    }
+}
+
+impl SpectralModelProcess<'_> {
+    // the values this block, for standing in a preset, see SpectralPlugin::preset
+    fn snapshot(&self) -> SpectralModel {
+        SpectralModel {
+            grain_select: *self.grain_select,
+            fuzz: *self.fuzz,
+            fuzz_slope: *self.fuzz_slope,
+            loss: *self.loss,
+            glitch_freq: *self.glitch_freq,
+            glitch_gain: *self.glitch_gain,
+            glitch_tilt: *self.glitch_tilt,
+            glitch_hold: *self.glitch_hold,
+            glitch_decay: *self.glitch_decay,
+            delay_select: *self.delay_select,
+            synthesis_select: *self.synthesis_select,
+            freeze: *self.freeze,
+            melt: *self.melt,
+            accumulate: *self.accumulate,
+            accumulate_clear: *self.accumulate_clear,
+            partials: *self.partials,
+            partial_detune: *self.partial_detune,
+            partial_decay: *self.partial_decay,
+            mix: *self.mix,
+            pitch: *self.pitch,
+            spread: *self.spread,
+            formant: *self.formant,
+            lifter: *self.lifter,
+            overlap: *self.overlap,
+            gate_attack: *self.gate_attack,
+            gate_release: *self.gate_release,
+            spectral_delay: *self.spectral_delay,
+            spectral_delay_feedback: *self.spectral_delay_feedback,
+            blur: *self.blur,
+            decay: *self.decay,
+            stereo_mode: *self.stereo_mode,
+            stereo_link: *self.stereo_link,
+            transport_reset: *self.transport_reset,
+            duck: *self.duck,
+            morph: *self.morph,
+            vocode: *self.vocode,
+            print_capture: *self.print_capture,
+            print: *self.print,
+            denoise_learn: *self.denoise_learn,
+            denoise: *self.denoise,
+            denoise_floor: *self.denoise_floor,
+            glitch_sync: *self.glitch_sync,
+            phase_mode: *self.phase_mode,
+            component: *self.component,
+            compress_threshold: *self.compress_threshold,
+            compress_ratio: *self.compress_ratio,
+            compress_attack: *self.compress_attack,
+            compress_release: *self.compress_release,
+            tilt: *self.tilt,
+            tilt_center: *self.tilt_center,
+            scramble: *self.scramble,
+            scramble_range: *self.scramble_range,
+            crush: *self.crush,
+            crush_db: *self.crush_db,
+            low_cut: *self.low_cut,
+            high_cut: *self.high_cut,
+            adaptive_loss: *self.adaptive_loss,
+            loss_invert: *self.loss_invert,
+            loss_db: *self.loss_db,
+            preserve_transients: *self.preserve_transients,
+            limiter: *self.limiter,
+            ceiling: *self.ceiling,
+            dc_block: *self.dc_block,
+            auto_gain: *self.auto_gain,
+            stretch: *self.stretch,
+            jitter: *self.jitter,
+            reverse: *self.reverse,
+            bypass: *self.bypass,
+            lfo1_rate: *self.lfo1_rate,
+            lfo1_sync: *self.lfo1_sync,
+            lfo1_depth: *self.lfo1_depth,
+            lfo1_shape: *self.lfo1_shape,
+            lfo1_target: *self.lfo1_target,
+            lfo2_rate: *self.lfo2_rate,
+            lfo2_sync: *self.lfo2_sync,
+            lfo2_depth: *self.lfo2_depth,
+            lfo2_shape: *self.lfo2_shape,
+            lfo2_target: *self.lfo2_target,
+            macro1: *self.macro1,
+            macro1_fuzz: *self.macro1_fuzz,
+            macro1_loss: *self.macro1_loss,
+            macro1_glitch_freq: *self.macro1_glitch_freq,
+            macro1_glitch_gain: *self.macro1_glitch_gain,
+            macro1_grain_select: *self.macro1_grain_select,
+            macro1_delay_select: *self.macro1_delay_select,
+            macro2: *self.macro2,
+            macro2_fuzz: *self.macro2_fuzz,
+            macro2_loss: *self.macro2_loss,
+            macro2_glitch_freq: *self.macro2_glitch_freq,
+            macro2_glitch_gain: *self.macro2_glitch_gain,
+            macro2_grain_select: *self.macro2_grain_select,
+            macro2_delay_select: *self.macro2_delay_select,
+            dice: *self.dice,
+            dice_fuzz_min: *self.dice_fuzz_min,
+            dice_fuzz_max: *self.dice_fuzz_max,
+            dice_loss_min: *self.dice_loss_min,
+            dice_loss_max: *self.dice_loss_max,
+            dice_glitch_freq_min: *self.dice_glitch_freq_min,
+            dice_glitch_freq_max: *self.dice_glitch_freq_max,
+            dice_glitch_gain_min: *self.dice_glitch_gain_min,
+            dice_glitch_gain_max: *self.dice_glitch_gain_max,
+        }
+    }
+}
+
+impl SpectralModel {
+    fn macro1_weights(&self) -> MacroWeights {
+        MacroWeights {
+            fuzz: self.macro1_fuzz,
+            loss: self.macro1_loss,
+            glitch_freq: self.macro1_glitch_freq,
+            glitch_gain: self.macro1_glitch_gain,
+            grain_select: self.macro1_grain_select,
+            delay_select: self.macro1_delay_select,
+        }
+    }
+
+    fn macro2_weights(&self) -> MacroWeights {
+        MacroWeights {
+            fuzz: self.macro2_fuzz,
+            loss: self.macro2_loss,
+            glitch_freq: self.macro2_glitch_freq,
+            glitch_gain: self.macro2_glitch_gain,
+            grain_select: self.macro2_grain_select,
+            delay_select: self.macro2_delay_select,
+        }
+    }
+}
+
+impl Into<SpectralDecayParameters> for &SpectralModel {
+    fn into(self) -> SpectralDecayParameters {
+        SpectralDecayParameters {
+            grain_select: self.grain_select,
+            fuzz: self.fuzz,
+            fuzz_slope: self.fuzz_slope,
+            loss: loss_param(self.loss, self.loss_db > 0.5),
+            glitch_freq: self.glitch_freq,
+            glitch_gain: self.glitch_gain,
+            glitch_tilt: self.glitch_tilt,
+            glitch_hold: self.glitch_hold,
+            glitch_decay: self.glitch_decay,
+            delay_select: self.delay_select,
+            synthesis_select: self.synthesis_select,
+            freeze: self.freeze > 0.5,
+            melt: self.melt,
+            accumulate: self.accumulate > 0.5,
+            partials: self.partials,
+            partial_detune: self.partial_detune,
+            partial_decay: self.partial_decay,
+            mix: self.mix,
+            pitch: self.pitch,
+            spread: self.spread,
+            formant: self.formant,
+            lifter: self.lifter,
+            overlap: match self.overlap {
+                x if x < 1. / 3. => Overlap::Half,
+                x if x < 2. / 3. => Overlap::ThreeQuarters,
+                _ => Overlap::SevenEighths
+            },
+            gate_attack: self.gate_attack,
+            gate_release: self.gate_release,
+            spectral_delay: self.spectral_delay,
+            spectral_delay_feedback: self.spectral_delay_feedback,
+            blur: self.blur,
+            decay: self.decay,
+            duck: self.duck,
+            morph: self.morph,
+            vocode: self.vocode,
+            print: self.print,
+            denoise: self.denoise,
+            denoise_floor: self.denoise_floor,
+            glitch_synced: glitch_division(self.glitch_sync).is_some(),
+            phase_mode: match self.phase_mode {
+                x if x < 1. / 3. => PhaseMode::Normal,
+                x if x < 2. / 3. => PhaseMode::Robot,
+                _ => PhaseMode::Whisper
+            },
+            component: match self.component {
+                x if x < 1. / 3. => Component::Both,
+                x if x < 2. / 3. => Component::Harmonic,
+                _ => Component::Percussive
+            },
+            compress_threshold: self.compress_threshold,
+            compress_ratio: self.compress_ratio,
+            compress_attack: self.compress_attack,
+            compress_release: self.compress_release,
+            tilt: self.tilt,
+            tilt_center: self.tilt_center,
+            scramble: self.scramble,
+            scramble_range: self.scramble_range,
+            crush: self.crush,
+            crush_db: self.crush_db > 0.5,
+            low_cut: self.low_cut,
+            high_cut: if self.high_cut >= 20000. { f32::INFINITY } else { self.high_cut },
+            adaptive_loss: self.adaptive_loss,
+            loss_invert: self.loss_invert > 0.5,
+            loss_db: self.loss_db > 0.5,
+            preserve_transients: self.preserve_transients,
+            limiter: self.limiter > 0.5,
+            ceiling: self.ceiling,
+            dc_block: self.dc_block > 0.5,
+            auto_gain: self.auto_gain > 0.5,
+            stretch: self.stretch,
+            jitter: self.jitter,
+            reverse: self.reverse,
+            bypass: self.bypass > 0.5,
+            lfo: [
+                lfo_params(self.lfo1_rate, self.lfo1_depth, self.lfo1_shape, self.lfo1_target),
+                lfo_params(self.lfo2_rate, self.lfo2_depth, self.lfo2_shape, self.lfo2_target),
+            ]
+        }
+    }
+}
+
+impl Default for SpectralModel {
+    fn default() -> Self {
+        Self {
+            grain_select: 0.5,
+            fuzz: 0.0,
+            fuzz_slope: 0.0,
+            loss: 0.5,
+            glitch_freq: 0.1,
+            glitch_gain: 100.,
+            glitch_tilt: 0.0,
+            glitch_hold: 0.0,
+            glitch_decay: 0.0,
+            delay_select: 0.0,
+            synthesis_select: 1.0,
+            freeze: 0.0,
+            melt: 0.0,
+            accumulate: 0.0,
+            accumulate_clear: 0.0,
+            partials: 0.0,
+            partial_detune: 0.0,
+            partial_decay: 0.0,
+            mix: 1.0,
+            pitch: 0.0,
+            spread: 1.0,
+            formant: 0.0,
+            lifter: 0.0,
+            overlap: 0.5,
+            gate_attack: 0.0,
+            gate_release: 0.0,
+            spectral_delay: 0.0,
+            spectral_delay_feedback: 0.0,
+            blur: 0.0,
+            decay: 0.0,
+            stereo_mode: 0.0,
+            stereo_link: 0.0,
+            transport_reset: 0.5,
+            duck: 0.0,
+            morph: 0.0,
+            vocode: 0.0,
+            print_capture: 0.0,
+            print: 0.0,
+            denoise_learn: 0.0,
+            denoise: 0.0,
+            denoise_floor: 0.1,
+            glitch_sync: 0.0,
+            phase_mode: 0.0,
+            component: 0.0,
+            compress_threshold: 0.0,
+            compress_ratio: 1.0,
+            compress_attack: 0.0,
+            compress_release: 0.0,
+            tilt: 0.0,
+            tilt_center: 1000.0,
+            scramble: 0.0,
+            scramble_range: 1.0,
+            crush: 0.0,
+            crush_db: 0.0,
+            low_cut: 0.0,
+            high_cut: 20000.0,
+            adaptive_loss: 0.0,
+            loss_invert: 0.0,
+            loss_db: 0.0,
+            preserve_transients: 0.0,
+            limiter: 0.0,
+            ceiling: 0.0,
+            dc_block: 0.0,
+            auto_gain: 1.0,
+            stretch: 1.0,
+            jitter: 0.0,
+            reverse: 0.0,
+            bypass: 0.0,
+            lfo1_rate: 1.0,
+            lfo1_sync: 0.0,
+            lfo1_depth: 0.0,
+            lfo1_shape: 0.0,
+            lfo1_target: 0.0,
+            lfo2_rate: 1.0,
+            lfo2_sync: 0.0,
+            lfo2_depth: 0.0,
+            lfo2_shape: 0.0,
+            lfo2_target: 0.0,
+            macro1: 0.0,
+            macro1_fuzz: 0.5,
+            macro1_loss: 0.8,
+            macro1_glitch_freq: 0.3,
+            macro1_glitch_gain: 0.5,
+            macro1_grain_select: 0.0,
+            macro1_delay_select: 0.0,
+            macro2: 0.0,
+            macro2_fuzz: 0.0,
+            macro2_loss: 0.0,
+            macro2_glitch_freq: 0.0,
+            macro2_glitch_gain: 0.0,
+            macro2_grain_select: 1.0,
+            macro2_delay_select: 0.0,
+            dice: 0.0,
+            dice_fuzz_min: 0.0,
+            dice_fuzz_max: 1.0,
+            dice_loss_min: 0.0000158,
+            dice_loss_max: 1.0,
+            dice_glitch_freq_min: 0.0,
+            dice_glitch_freq_max: 1.0,
+            dice_glitch_gain_min: 1.0,
+            dice_glitch_gain_max: 100.0
+        }
+    }
+}
+
+// mid/side blocks are encoded into scratch buffers this long
+const MS_BLOCK: usize = 512;
+
+// general purpose button 5, rolls the dice
+const DICE_CC: u8 = 80;
+
+// playback going back by more than this from where the last block ended is a loop, not rounding
+const LOOP_TOLERANCE_BEATS: f64 = 0.01;
+
+// main channels, picked at build time since the host gets them as constants,
+// stereo unless built with the surround (5.1) or ambisonic (third order) feature
+#[cfg(not(any(feature = "surround", feature = "ambisonic")))]
+const CHANNELS: usize = 2;
+#[cfg(feature = "surround")]
+const CHANNELS: usize = 6;
+#[cfg(all(feature = "ambisonic", not(feature = "surround")))]
+const CHANNELS: usize = 16;
+
+struct SpectralPlugin {
+    sample_rate: f32,
+    was_playing: bool,
+    // the beat the last block ended on
+    next_beat: f64,
+    // split between a few threads, see ChannelGroup
+    sd: ChannelGroup,
+    // mid, side, sidechain mid, sidechain side of the first two channels
+    ms_in: [[f32; MS_BLOCK]; 4],
+    ms_out: [[f32; MS_BLOCK]; 2],
+    // one bit per held midi note, and the velocity of the latest note-on
+    notes: u128,
+    velocity: f32,
+    rng: FastRng,
+    // what all channels are seeded with while linked
+    link_seed: u64,
+    dice_high: bool,
+    dice_midi: bool,
+    print_capturing: bool,
+    denoise_learning: bool,
+    accumulate_clearing: bool,
+    // the fuzz, loss and glitch knobs when the dice was rolled, and what it rolled
+    rolled: Option<([f32; 4], SpectralDecayParameters)>,
+    // the factory bank, and the program change waiting for the next block
+    presets: Vec<SpectralModel>,
+    program: Option<usize>,
+    // the knobs when a preset was picked, and the preset
+    preset: Option<(SpectralModel, SpectralModel)>,
+    #[cfg(feature = "editor")]
+    editor: Arc<editor::EditorLink>,
+    // the editor's edits picked up so far
+    #[cfg(feature = "editor")]
+    edits_seen: u32,
+}
+
+impl Plugin for SpectralPlugin {
+    const NAME: &'static str = "Spectral Decay";
+    const PRODUCT: &'static str = "Spectral Decay";
+    const VENDOR: &'static str = "Conundrumer";
+
+    // main channels, then as many sidechain channels
+    const INPUT_CHANNELS: usize = 2 * CHANNELS;
+    const OUTPUT_CHANNELS: usize = CHANNELS;
+
+    type Model = SpectralModel;
+
+    #[inline]
+    fn new(sample_rate: f32, _model: &SpectralModel) -> Self {
+        let grain_sizes = &fft_sizes::default_sizes(sample_rate);
+        // all channels share one set of windows
+        let table = Arc::new(GrainTable::new(grain_sizes, WindowKind::Hann).unwrap());
+        let channel = || {
+            let mut sd = SpectralDecay::with_table(table.clone());
+            sd.set_sample_rate(sample_rate);
+            sd
+        };
+        #[allow(unused_mut)]
+        let mut channels: Vec<_> = (0..CHANNELS).map(|_| channel()).collect();
+        #[cfg(feature = "editor")]
+        let editor = editor::EditorLink::new(channels[0].spectrum_tap(), sample_rate);
+        let mut rng = FastRng::new();
+        let link_seed = rng.gen();
+        Self {
+            sample_rate,
+            was_playing: false,
+            next_beat: 0.,
+            sd: ChannelGroup::new(channels),
+            ms_in: [[0.; MS_BLOCK]; 4],
+            ms_out: [[0.; MS_BLOCK]; 2],
+            notes: 0,
+            velocity: 0.,
+            rng,
+            link_seed,
+            dice_high: false,
+            dice_midi: false,
+            print_capturing: false,
+            denoise_learning: false,
+            accumulate_clearing: false,
+            rolled: None,
+            presets: presets::factory().into_iter().map(|(_, preset)| preset).collect(),
+            program: None,
+            preset: None,
+            #[cfg(feature = "editor")]
+            editor,
+            #[cfg(feature = "editor")]
+            edits_seen: 0,
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, model: &SpectralModelProcess, ctx: &mut ProcessContext<Self>) {
+        let input = &ctx.inputs[0].buffers;
+        let output = &mut ctx.outputs[0].buffers;
+        // a factory preset picked by program change stands in for every knob until one of them is moved
+        let snapshot = model.snapshot();
+        #[cfg(feature = "editor")]
+        if let Some(program) = self.editor.take_program() {
+            self.program = Some(program);
+        }
+        if let Some(preset) = self.program.take().and_then(|i| self.presets.get(i)) {
+            self.preset = Some((snapshot.clone(), preset.clone()));
+        }
+        #[allow(unused_mut)]
+        let mut model = match &self.preset {
+            Some((at, preset)) if *at == snapshot => preset.clone(),
+            _ => {
+                self.preset = None;
+                snapshot.clone()
+            }
+        };
+        // and so do the editor's knobs, on top of any preset
+        #[cfg(feature = "editor")]
+        {
+            if let Some(knobs) = self.editor.take_edits(&mut self.edits_seen) {
+                editor::set_knobs(&mut model, knobs);
+                self.preset = Some((snapshot, model.clone()));
+            }
+            self.editor.publish(&model, self.edits_seen);
+        }
+
+        let mut params: SpectralDecayParameters = (&model).into();
+
+        let knobs = [model.fuzz, model.loss, model.glitch_freq, model.glitch_gain];
+        let dice_high = model.dice > 0.5;
+        if (dice_high && !self.dice_high) || std::mem::take(&mut self.dice_midi) {
+            let mut rolled = params;
+            rolled.randomize(&mut self.rng, &RandomizeConstraints {
+                fuzz: (model.dice_fuzz_min, model.dice_fuzz_max),
+                loss: (model.dice_loss_min, model.dice_loss_max),
+                glitch_freq: (model.dice_glitch_freq_min, model.dice_glitch_freq_max),
+                glitch_gain: (model.dice_glitch_gain_min, model.dice_glitch_gain_max),
+            });
+            // the loss is rolled as a knob level, evenly over the knob's taper, and mapped like the knob
+            let (lo, hi) = (model.dice_loss_min.max(loss_floor()), model.dice_loss_max.max(loss_floor()));
+            let level = lo * (hi / lo).powf(self.rng.gen::<u32>() as f32 / u32::MAX as f32);
+            rolled.loss = loss_param(level, model.loss_db > 0.5);
+            self.rolled = Some((knobs, rolled));
+        }
+        self.dice_high = dice_high;
+        match self.rolled {
+            Some((at, rolled)) if at == knobs => {
+                params.fuzz = rolled.fuzz;
+                params.loss = rolled.loss;
+                params.glitch_freq = rolled.glitch_freq;
+                params.glitch_gain = rolled.glitch_gain;
+            }
+            _ => self.rolled = None,
+        }
+
+        let macros = [(model.macro1, model.macro1_weights()), (model.macro2, model.macro2_weights())];
+        for (amount, weights) in macros.iter() {
+            weights.apply(&mut params, *amount);
+        }
+
+        // a held note freezes, and how hard it was hit sets how loud the glitches get
+        if self.notes != 0 {
+            params.freeze = true;
+            params.glitch_gain = 1. + (params.glitch_gain - 1.) * self.velocity;
+        }
+
+        // synced lfos complete a cycle every division
+        let time = ctx.musical_time;
+        for (lfo, &sync) in params.lfo.iter_mut().zip(&[model.lfo1_sync, model.lfo2_sync]) {
+            if let (Some(division), true) = (lfo_division(sync), time.bpm > 0.) {
+                lfo.rate = (time.bpm / 60. / division) as f32;
+            }
+        }
+
+        // the host is never told about the delay: baseplug leaves VST2's initialDelay at 0 and has no ioChanged call,
+        // so latency_changed() goes unused here. "Delay compensation" at the largest "Grain size" keeps the delay fixed
+        for sd in self.sd.iter_mut() {
+            sd.set_params(params);
+        }
+
+        // captured prints are lost when the session reloads: baseplug only saves the model, where every field
+        // has to be a smoothable f32 parameter, and it has no hook for extra state. spectral_print::save keeps one
+        let print_capturing = model.print_capture > 0.5;
+        if print_capturing != self.print_capturing {
+            for sd in self.sd.iter_mut() {
+                if print_capturing {
+                    sd.start_print_capture();
+                } else {
+                    sd.finish_print_capture();
+                }
+            }
+            self.print_capturing = print_capturing;
+        }
+
+        // learned noise profiles aren't kept in the session either, for the same reason
+        let denoise_learning = model.denoise_learn > 0.5;
+        if denoise_learning != self.denoise_learning {
+            for sd in self.sd.iter_mut() {
+                if denoise_learning {
+                    sd.start_noise_learning();
+                } else {
+                    sd.finish_noise_learning();
+                }
+            }
+            self.denoise_learning = denoise_learning;
+        }
+
+        let accumulate_clearing = model.accumulate_clear > 0.5;
+        if accumulate_clearing && !self.accumulate_clearing {
+            for sd in self.sd.iter_mut() {
+                sd.clear_accumulation();
+            }
+        }
+        self.accumulate_clearing = accumulate_clearing;
+
+        let seed = if model.stereo_link > 0.5 { Some(self.link_seed) } else { None };
+        for sd in self.sd.iter_mut() {
+            sd.set_seed(seed);
+        }
+
+        let nframes = input[0].len();
+        let mid_side = model.stereo_mode >= 0.5;
+        // skip analyzing the sidechain when it isn't used
+        let sidechained = params.duck > 0. || params.morph > 0. || params.vocode > 0.;
+
+        // don't replay stale tails when playback restarts, or the end of a looped section over its start
+        let (on_stop, on_start) = match model.transport_reset {
+            x if x < 1. / 3. => (false, false),
+            x if x < 2. / 3. => (true, false),
+            _ => (true, true)
+        };
+        let stopped = self.was_playing && !time.is_playing;
+        let started = !self.was_playing && time.is_playing;
+        let looped = self.was_playing && time.is_playing && time.bpm > 0. && time.beat < self.next_beat - LOOP_TOLERANCE_BEATS;
+        if (on_stop && stopped) || (on_start && (started || looped)) {
+            for sd in self.sd.iter_mut() {
+                sd.reset();
+            }
+        }
+        self.was_playing = time.is_playing;
+        self.next_beat = time.beat + nframes as f64 * time.bpm / 60. / self.sample_rate as f64;
+
+        let mut start = 0;
+
+        // split the block at each note division so the glitch lands on the frame closest to it
+        if let (Some(division), true) = (glitch_division(model.glitch_sync), time.is_playing && time.bpm > 0.) {
+            let beats_per_sample = time.bpm / 60. / self.sample_rate as f64;
+            let mut next = (time.beat / division).ceil() * division;
+
+            loop {
+                // the next block starts past a division in its last half sample, so that one still lands in this block
+                let offset = (next - time.beat) / beats_per_sample;
+                if offset >= nframes as f64 {
+                    break;
+                }
+                let end = (offset.round() as usize).min(nframes);
+                self.process_range(input, output, start..end, mid_side, sidechained);
+
+                for sd in self.sd.iter_mut() {
+                    sd.trigger_glitch();
+                }
+
+                start = end;
+                next += division;
+            }
+        }
+
+        self.process_range(input, output, start..nframes, mid_side, sidechained);
+    }
+}
+
+impl MidiReceiver for SpectralPlugin {
+    fn midi_input(&mut self, _model: &SpectralModelProcess, data: [u8; 3]) {
+        let note = 1u128 << (data[1] & 0x7f);
+        match data[0] & 0xf0 {
+            // note-on with zero velocity is a note-off
+            0x90 if data[2] > 0 => {
+                self.notes |= note;
+                self.velocity = data[2] as f32 / 127.;
+            }
+            0x80 | 0x90 => self.notes &= !note,
+            // the dice is a button, rolling when it's pressed
+            0xb0 if data[1] == DICE_CC && data[2] >= 64 => self.dice_midi = true,
+            0xc0 => self.program = Some(data[1] as usize),
+            _ => (),
+        }
+    }
+}
+
+impl SpectralPlugin {
+    fn process_range(&mut self, input: &[&[f32]], output: &mut [&mut [f32]], range: Range<usize>, mid_side: bool, sidechained: bool) {
+        let sidechain = |c: usize, range: Range<usize>| if sidechained { Some(&input[c + CHANNELS][range]) } else { None };
+
+        if !mid_side {
+            let mut outputs = output.iter_mut();
+            let mut channels: [ChannelIo; CHANNELS] = std::array::from_fn(|c| ChannelIo {
+                input: &input[c][range.clone()],
+                sidechain: sidechain(c, range.clone()),
+                output: &mut outputs.next().unwrap()[range.clone()],
+            });
+            self.sd.process(&mut channels);
+            return;
+        }
+
+        // mid/side of the first two channels, the rest go through as they are
+        for start in range.clone().step_by(MS_BLOCK) {
+            let end = (start + MS_BLOCK).min(range.end);
+            let len = end - start;
+
+            let [mid, side, sc_mid, sc_side] = &mut self.ms_in;
+            for (i, (l, r)) in input[0][start..end].iter().zip(&input[1][start..end]).enumerate() {
+                mid[i] = (l + r) * 0.5;
+                side[i] = (l - r) * 0.5;
+            }
+            if sidechained {
+                for (i, (l, r)) in input[CHANNELS][start..end].iter().zip(&input[CHANNELS + 1][start..end]).enumerate() {
+                    sc_mid[i] = (l + r) * 0.5;
+                    sc_side[i] = (l - r) * 0.5;
+                }
+            }
+            let ms_in = [&mid[..len], &side[..len]];
+            let ms_sidechain = [&sc_mid[..len], &sc_side[..len]];
+
+            let mut ms_outs = self.ms_out.iter_mut();
+            let (_, rest) = output.split_at_mut(2);
+            let mut outputs = rest.iter_mut();
+            let mut channels: [ChannelIo; CHANNELS] = std::array::from_fn(|c| if c < 2 {
+                ChannelIo {
+                    input: ms_in[c],
+                    sidechain: if sidechained { Some(ms_sidechain[c]) } else { None },
+                    output: &mut ms_outs.next().unwrap()[..len],
+                }
+            } else {
+                ChannelIo {
+                    input: &input[c][start..end],
+                    sidechain: sidechain(c, start..end),
+                    output: &mut outputs.next().unwrap()[start..end],
+                }
+            });
+            self.sd.process(&mut channels);
+
+            let [mid_out, side_out] = &self.ms_out;
+            for (i, (m, s)) in mid_out[..len].iter().zip(&side_out[..len]).enumerate() {
+                output[0][start + i] = m + s;
+                output[1][start + i] = m - s;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "editor")]
+impl PluginUI for SpectralPlugin {
+    type Handle = baseview::WindowHandle;
+
+    fn ui_size() -> (i16, i16) {
+        editor::SIZE
+    }
+
+    fn ui_open(parent: &impl HasRawWindowHandle) -> Result<Self::Handle, ()> {
+        editor::open(parent)
+    }
+
+    fn ui_close(mut handle: Self::Handle) {
+        handle.close();
+    }
+
+    // the editor follows what the plugin runs with every block, overrides included, rather than the host's values
+    fn ui_param_notify(_handle: &Self::Handle, _param: &'static Param<Self, <Self::Model as Model<Self>>::Smooth>, _val: f32) {}
+}
+
+baseplug::vst2!(SpectralPlugin, b"SpDc");
\ No newline at end of file
@@ -0,0 +1,220 @@
+// the plugin's window, only built with the editor feature: sliders for the six main knobs,
+// the factory presets and a live spectrum of the first channel
+
+use super::{presets, SpectralModel};
+use crate::SpectrumTap;
+use baseview::{Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
+use egui::{vec2, Color32, CtxRef, Pos2, Sense, Shape, Slider, Stroke};
+use egui_baseview::{EguiWindow, Queue, RenderSettings, Settings};
+use raw_window_handle::HasRawWindowHandle;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+pub const SIZE: (i16, i16) = (480, 400);
+
+const SPECTRUM_HEIGHT: f32 = 160.;
+// the spectrum's range, in Hz and dB relative to a full scale sine
+const MIN_FREQ: f32 = 20.;
+const MAX_FREQ: f32 = 20000.;
+const MIN_DB: f32 = -96.;
+
+// nothing waiting in EditorLink::program
+const NO_PROGRAM: usize = usize::MAX;
+
+struct Knob {
+    name: &'static str,
+    min: f32,
+    max: f32,
+    logarithmic: bool,
+}
+
+// the six knobs the editor has sliders for, in the order of knobs() and set_knobs()
+const KNOBS: [Knob; 6] = [
+    Knob { name: "Grain size", min: 0., max: 1., logarithmic: false },
+    Knob { name: "Fuzz", min: 0., max: 1., logarithmic: false },
+    // the bottom is off, see loss_floor()
+    Knob { name: "Loss", min: 0.0000158, max: 1., logarithmic: true },
+    Knob { name: "Glitch frequency", min: 0., max: 1., logarithmic: false },
+    Knob { name: "Glitch gain", min: 1., max: 100., logarithmic: true },
+    Knob { name: "Delay compensation", min: 0., max: 1., logarithmic: false },
+];
+
+pub fn knobs(model: &SpectralModel) -> [f32; 6] {
+    [model.grain_select, model.fuzz, model.loss, model.glitch_freq, model.glitch_gain, model.delay_select]
+}
+
+pub fn set_knobs(model: &mut SpectralModel, knobs: [f32; 6]) {
+    let [grain_select, fuzz, loss, glitch_freq, glitch_gain, delay_select] = knobs;
+    model.grain_select = grain_select;
+    model.fuzz = fuzz;
+    model.loss = loss;
+    model.glitch_freq = glitch_freq;
+    model.glitch_gain = glitch_gain;
+    model.delay_select = delay_select;
+}
+
+// what a plugin instance and its editor share, the plugin's side never locks or allocates
+// the editor can't set host parameters, so its knobs and presets stand in for them like a program change does
+pub struct EditorLink {
+    // as f32 bits, what the plugin is running with, and what the editor last set them to
+    shown: [AtomicU32; 6],
+    edited: [AtomicU32; 6],
+    // how many times the editor has set them, and how many of those the plugin has picked up
+    edits: AtomicU32,
+    applied: AtomicU32,
+    // a factory preset picked in the editor, NO_PROGRAM when there's none waiting
+    program: AtomicUsize,
+    sample_rate: AtomicU32,
+    // only the editor reads it, the lock is never taken on the audio thread
+    spectrum: Mutex<SpectrumTap>,
+}
+
+// baseplug opens an editor without saying for which instance, so it goes with the one created last
+static LATEST: Mutex<Option<Weak<EditorLink>>> = Mutex::new(None);
+
+impl EditorLink {
+    // made along with the plugin instance, off the audio thread
+    pub fn new(spectrum: SpectrumTap, sample_rate: f32) -> Arc<Self> {
+        let zero = || AtomicU32::new(0);
+        let link = Arc::new(Self {
+            shown: [zero(), zero(), zero(), zero(), zero(), zero()],
+            edited: [zero(), zero(), zero(), zero(), zero(), zero()],
+            edits: zero(),
+            applied: zero(),
+            program: AtomicUsize::new(NO_PROGRAM),
+            sample_rate: AtomicU32::new(sample_rate.to_bits()),
+            spectrum: Mutex::new(spectrum),
+        });
+        *LATEST.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::downgrade(&link));
+        link
+    }
+
+    // the preset picked since the last call
+    pub fn take_program(&self) -> Option<usize> {
+        match self.program.swap(NO_PROGRAM, Ordering::AcqRel) {
+            NO_PROGRAM => None,
+            program => Some(program),
+        }
+    }
+
+    // the knobs as set in the editor, if they were since seen
+    pub fn take_edits(&self, seen: &mut u32) -> Option<[f32; 6]> {
+        let edits = self.edits.load(Ordering::Acquire);
+        if edits == *seen {
+            return None;
+        }
+        *seen = edits;
+        let mut knobs = [0.; 6];
+        for (x, edited) in knobs.iter_mut().zip(self.edited.iter()) {
+            *x = f32::from_bits(edited.load(Ordering::Relaxed));
+        }
+        Some(knobs)
+    }
+
+    // what the plugin ran this block with, having picked up the edits up to seen
+    pub fn publish(&self, model: &SpectralModel, seen: u32) {
+        for (shown, x) in self.shown.iter().zip(knobs(model).iter()) {
+            shown.store(x.to_bits(), Ordering::Relaxed);
+        }
+        self.applied.store(seen, Ordering::Release);
+    }
+}
+
+struct EditorState {
+    link: Arc<EditorLink>,
+    presets: Vec<&'static str>,
+    preset: Option<usize>,
+    knobs: [f32; 6],
+    // the last edit sent, the plugin's knobs are shown again once it's picked that one up
+    sent: u32,
+}
+
+impl EditorState {
+    fn update(&mut self, ctx: &CtxRef) {
+        let link = self.link.clone();
+        let applied = link.applied.load(Ordering::Acquire);
+        if applied.wrapping_sub(self.sent) as i32 >= 0 {
+            for (x, shown) in self.knobs.iter_mut().zip(link.shown.iter()) {
+                *x = f32::from_bits(shown.load(Ordering::Relaxed));
+            }
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let selected = self.preset.map_or("Preset", |i| self.presets[i]);
+            egui::ComboBox::from_label("").selected_text(selected).show_ui(ui, |ui| {
+                for (i, name) in self.presets.iter().enumerate() {
+                    if ui.selectable_label(self.preset == Some(i), *name).clicked() {
+                        self.preset = Some(i);
+                        link.program.store(i, Ordering::Release);
+                    }
+                }
+            });
+
+            let mut changed = false;
+            for (knob, x) in KNOBS.iter().zip(self.knobs.iter_mut()) {
+                let slider = Slider::new(x, knob.min..=knob.max).logarithmic(knob.logarithmic).text(knob.name);
+                changed |= ui.add(slider).changed();
+            }
+            if changed {
+                for (edited, x) in link.edited.iter().zip(self.knobs.iter()) {
+                    edited.store(x.to_bits(), Ordering::Relaxed);
+                }
+                self.sent = link.edits.fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+            }
+
+            let sample_rate = f32::from_bits(link.sample_rate.load(Ordering::Relaxed));
+            let mut spectrum = link.spectrum.lock().unwrap_or_else(|e| e.into_inner());
+            let (bins, fft_size) = spectrum.read();
+            let (response, painter) = ui.allocate_painter(vec2(ui.available_width(), SPECTRUM_HEIGHT), Sense::hover());
+            let rect = response.rect;
+            painter.rect_filled(rect, 0., Color32::from_gray(16));
+
+            // log frequency across, dB up
+            let octaves = (MAX_FREQ / MIN_FREQ).log2();
+            let points: Vec<_> = bins.iter().enumerate().skip(1).filter_map(|(k, &x)| {
+                let freq = k as f32 * sample_rate / fft_size as f32;
+                if freq < MIN_FREQ || freq > MAX_FREQ {
+                    return None;
+                }
+                let db = (20. * x.max(1e-6).log10()).max(MIN_DB).min(0.);
+                Some(Pos2::new(
+                    rect.left() + rect.width() * (freq / MIN_FREQ).log2() / octaves,
+                    rect.top() + rect.height() * db / MIN_DB,
+                ))
+            }).collect();
+            painter.add(Shape::line(points, Stroke::new(1., Color32::LIGHT_GREEN)));
+        });
+
+        // keep the spectrum moving
+        ctx.request_repaint();
+    }
+}
+
+// the editor for the instance created last, in parent
+pub fn open(parent: &impl HasRawWindowHandle) -> Result<WindowHandle, ()> {
+    let link = LATEST.lock().unwrap_or_else(|e| e.into_inner()).as_ref().and_then(Weak::upgrade).ok_or(())?;
+    let sent = link.edits.load(Ordering::Acquire);
+    let state = EditorState {
+        link,
+        presets: presets::factory().into_iter().map(|(name, _)| name).collect(),
+        preset: None,
+        knobs: [0.; 6],
+        sent,
+    };
+    let settings = Settings {
+        window: WindowOpenOptions {
+            title: String::from("Spectral Decay"),
+            size: Size::new(SIZE.0 as f64, SIZE.1 as f64),
+            scale: WindowScalePolicy::SystemScaleFactor,
+        },
+        render_settings: RenderSettings::default(),
+    };
+
+    Ok(EguiWindow::open_parented(
+        parent,
+        settings,
+        state,
+        |_ctx: &CtxRef, _queue: &mut Queue, _state: &mut EditorState| {},
+        |ctx: &CtxRef, _queue: &mut Queue, state: &mut EditorState| state.update(ctx),
+    ))
+}
@@ -0,0 +1,67 @@
+use super::SpectralModel;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// snapshots of every parameter, stored as json
+
+// the built-in bank, midi program change n picks the nth
+pub fn factory() -> Vec<(&'static str, SpectralModel)> {
+    vec![
+        ("Init", SpectralModel::default()),
+        ("Tape Rot", SpectralModel {
+            loss: 0.7,
+            fuzz: 0.15,
+            glitch_freq: 0.02,
+            gate_release: 12.,
+            blur: 0.6,
+            tilt: -3.,
+            ..SpectralModel::default()
+        }),
+        ("Glitch Storm", SpectralModel {
+            grain_select: 0.3,
+            loss: 0.3,
+            glitch_freq: 0.8,
+            glitch_gain: 60.,
+            scramble: 0.3,
+            scramble_range: 16.,
+            limiter: 1.,
+            ceiling: -1.,
+            ..SpectralModel::default()
+        }),
+        ("Frozen Choir", SpectralModel {
+            grain_select: 0.9,
+            freeze: 1.,
+            pitch: 12.,
+            mix: 0.6,
+            overlap: 1.,
+            ..SpectralModel::default()
+        }),
+        ("Robot", SpectralModel {
+            grain_select: 0.4,
+            loss: 0.2,
+            glitch_freq: 0.,
+            phase_mode: 0.5,
+            ..SpectralModel::default()
+        }),
+        ("Whisper Wash", SpectralModel {
+            grain_select: 0.7,
+            loss: 0.4,
+            glitch_freq: 0.,
+            phase_mode: 1.,
+            decay: 0.9,
+            ..SpectralModel::default()
+        }),
+    ]
+}
+
+pub fn save(path: impl AsRef<Path>, model: &SpectralModel) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(model)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, json)
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<SpectralModel> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -1,6 +1,18 @@
 use std::ops::Range;
 use std::fmt::Debug;
 
+// splits len elements from start of a buffer of size into the part before wrapping around and the part after
+// returns where to split the buffer, and the range of the first part in the second half of the split
+pub(crate) fn split_range(size: usize, start: usize, len: usize) -> (usize, Range<usize>) {
+    let end = start + len;
+    if end <= size {
+        (0, start..end)
+    } else {
+        let end = end - size;
+        (end, start - end..size - end)
+    }
+}
+
 #[derive(Debug)]
 pub struct RingBuffer<T> {
     data: Vec<T>,
@@ -17,11 +29,41 @@ impl<T: Copy + Default> RingBuffer<T> {
             len: if filled { size } else { 0 }
         }
     }
+    // full of default values, like new(size, true)
+    pub fn clear_fill(&mut self) {
+        for x in self.data.iter_mut() {
+            *x = Default::default();
+        }
+        self.start = 0;
+        self.len = self.size;
+    }
+    // keeps the most recent contents that fit
+    // a full buffer stays full, with default values before the old contents when growing
+    pub fn resize(&mut self, new_size: usize) {
+        let full = self.len == self.size;
+        let keep = self.len.min(new_size);
+        let len = if full { new_size } else { keep };
+
+        let mut data = vec![Default::default(); new_size];
+        if keep > 0 {
+            for (y, x) in data[len - keep..len].iter_mut().zip(self.iter(-(keep as isize))) {
+                *y = *x;
+            }
+        }
+
+        self.data = data;
+        self.size = new_size;
+        self.start = 0;
+        self.len = len;
+    }
 }
 impl<T> RingBuffer<T> {
     pub fn len(&self) -> usize {
         self.len
     }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
     fn end(&self) -> usize {
         let end = self.start + self.len;
         if end >= self.size {
@@ -29,10 +71,6 @@ impl<T> RingBuffer<T> {
         }
         return end;
     }
-    // pub fn clear_fill(&mut self) {
-    //     self.start = 0;
-    //     self.len = self.size;
-    // }
     // pub fn clear(&mut self) {
     //     self.start = 0;
     //     self.len = 0;
@@ -46,13 +84,7 @@ impl<T> RingBuffer<T> {
         }
     }
     fn split_range(&self, start: usize, len: usize) -> (usize, Range<usize>) {
-        let end = start + len;
-        if end <= self.size {
-            (0, start..end)
-        } else {
-            let end = end - self.size;
-            (end, start - end..self.size - end)
-        }
+        split_range(self.size, start, len)
     }
     fn split_range_relative(&self, start: isize) -> (usize, Range<usize>) {
         assert!(start < self.len as isize);
@@ -108,6 +140,17 @@ impl<T> RingBuffer<T> {
 
         (head, tail)
     }
+    // oldest contents first, the second slice is whatever wrapped around
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (split, range) = self.split_range(self.start, self.len);
+        let (tail, head) = self.data.split_at(split);
+        (&head[range], tail)
+    }
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (split, range) = self.split_range(self.start, self.len);
+        let (tail, head) = self.data.split_at_mut(split);
+        (&mut head[range], tail)
+    }
     pub fn iter_append(&mut self, len: usize) -> impl Iterator<Item = &mut T> {
         let (head, tail) = self.slices_append(len);
         head.iter_mut().chain(tail.iter_mut())
@@ -151,6 +194,16 @@ impl<T: Copy> RingBuffer<T> {
         dst[..split].copy_from_slice(&head);
         dst[split..].copy_from_slice(&tail);
     }
+    // like copy_append, but drops the oldest elements to make room
+    pub fn push_overwrite(&mut self, value: T) {
+        self.extend_overwrite(&[value]);
+    }
+    pub fn extend_overwrite(&mut self, src: &[T]) {
+        let src = &src[src.len().saturating_sub(self.size)..];
+        let overflow = (self.len + src.len()).saturating_sub(self.size);
+        self.remove(overflow);
+        self.copy_append(src);
+    }
 }
 
 impl<T: Copy + Default> RingBuffer<T> {
@@ -250,4 +303,65 @@ mod tests {
         dbg!(&buf);
         buf.copy_replace(Some(&[2;2]), None);
     }
+
+    #[test]
+    fn ring_buffer_slices() {
+        let mut buf = RingBuffer::<u8>::new(8, false);
+        buf.copy_append(&[0; 7]);
+        buf.remove(7);
+        buf.copy_append(&[13; 3]);
+
+        buf.copy_append(&[14; 4]);
+        let (head, tail) = buf.as_slices();
+        // wrapped around
+        assert_eq!((head.len(), tail.len()), (1, 6));
+        assert!(head.iter().chain(tail).copied().eq(buf.iter(0).copied()));
+
+        let (head, tail) = buf.as_mut_slices();
+        head.iter_mut().chain(tail.iter_mut()).for_each(|x| *x += 1);
+        assert!(buf.iter(0).copied().eq([14, 14, 14, 15, 15, 15, 15].iter().copied()));
+    }
+
+    #[test]
+    fn ring_buffer_clear_fill() {
+        let mut buf = RingBuffer::<u8>::new(4, false);
+        buf.copy_append(&[1, 2]);
+
+        buf.clear_fill();
+        assert_eq!(buf.len(), 4);
+        assert!(buf.iter(0).all(|&x| x == 0));
+    }
+
+    #[test]
+    fn ring_buffer_overwrite() {
+        let mut buf = RingBuffer::<u8>::new(4, false);
+        buf.extend_overwrite(&[1, 2, 3]);
+        buf.push_overwrite(4);
+        buf.push_overwrite(5);
+        assert!(buf.iter(0).copied().eq(2..6));
+
+        buf.extend_overwrite(&[6, 7, 8, 9, 10, 11]);
+        assert_eq!(buf.len(), 4);
+        assert!(buf.iter(0).copied().eq(8..12));
+    }
+
+    #[test]
+    fn ring_buffer_resize() {
+        let mut buf = RingBuffer::<u8>::new(4, true);
+        buf.copy_replace(Some(&[1, 2, 3]), None);
+
+        buf.resize(6);
+        assert_eq!(buf.len(), 6);
+        assert!(buf.iter(0).copied().eq([0, 0, 0, 1, 2, 3].iter().copied()));
+
+        buf.resize(2);
+        assert!(buf.iter(0).copied().eq([2, 3].iter().copied()));
+
+        let mut buf = RingBuffer::<u8>::new(8, false);
+        buf.copy_append(&[1, 2, 3]);
+        buf.resize(4);
+        assert!(buf.iter(0).copied().eq([1, 2, 3].iter().copied()));
+        buf.copy_append(&[4]);
+        assert_eq!(buf.len(), 4);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use random_fast_rng::FastRng;
+
+// FastRng::new() seeds from the system clock, which wasm32-unknown-unknown doesn't have,
+// so there every generator takes the next of a fixed sequence of seeds instead
+
+// the same seed gives the same sequence, for keeping generators in step
+pub(crate) fn seeded_rng(seed: u64) -> FastRng {
+    FastRng::seed(seed, 0xda3e_39cb_94b9_5bdb)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn new_rng() -> FastRng {
+    FastRng::new()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn new_rng() -> FastRng {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT_SEED: AtomicU64 = AtomicU64::new(0x853c_49e6_748f_ea9b);
+    seeded_rng(NEXT_SEED.fetch_add(1, Ordering::Relaxed))
+}
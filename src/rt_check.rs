@@ -0,0 +1,118 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// debug checks that the audio path doesn't allocate, free, lock or panic, only built with the rt-check feature
+// it replaces the global allocator of whatever links this crate with one that counts calls per thread,
+// and SpectralDecay::process and set_params assert none were made while they ran
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static ON_AUDIO_PATH: Cell<bool> = const { Cell::new(false) };
+}
+
+struct CountingAlloc;
+
+fn count() {
+    // try_with, the thread locals may already be gone while a thread shuts down
+    let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        count();
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|n| n.get())
+}
+
+// held for the length of a call on the audio path, checking it on the way out
+pub(crate) struct AudioPath {
+    name: &'static str,
+    allocations: usize,
+    // false when nested in another call on the audio path, which checks the whole thing
+    outer: bool,
+}
+
+impl AudioPath {
+    pub(crate) fn enter(name: &'static str) -> Self {
+        let outer = !ON_AUDIO_PATH.with(|x| x.replace(true));
+        Self { name, allocations: allocations(), outer }
+    }
+}
+
+impl Drop for AudioPath {
+    fn drop(&mut self) {
+        if !self.outer {
+            return;
+        }
+        ON_AUDIO_PATH.with(|x| x.set(false));
+        if std::thread::panicking() {
+            eprintln!("rt-check: {} panicked", self.name);
+            return;
+        }
+        let allocations = allocations() - self.allocations;
+        assert!(allocations == 0, "rt-check: {} allocated or freed {} times", self.name, allocations);
+    }
+}
+
+// for whatever has to stay off the audio path, e.g. taking a lock
+pub(crate) fn assert_off_audio_path(what: &str) {
+    assert!(!ON_AUDIO_PATH.with(|x| x.get()), "rt-check: {} on the audio path", what);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SpectralDecay, SpectralDecayParameters};
+
+    #[test]
+    fn audio_path_clean() {
+        let mut sd = SpectralDecay::new(&[32, 64]);
+        let input: Vec<_> = (0..1024).map(|x| (x as f32 * 0.1).sin()).collect();
+        let mut output = vec![0.; input.len()];
+
+        let mut p = SpectralDecayParameters::default();
+        for &grain_select in &[0., 1., 0.5] {
+            p.grain_select = grain_select;
+            p.loss = 0.3;
+            p.pitch = 3.;
+            sd.set_params(p);
+            sd.process(&input, &mut output);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "allocated or freed")]
+    fn audio_path_allocating() {
+        let _audio = AudioPath::enter("test");
+        drop(std::hint::black_box(vec![0u8; 16]));
+    }
+
+    #[test]
+    #[should_panic(expected = "on the audio path")]
+    fn audio_path_locking() {
+        let _audio = AudioPath::enter("test");
+        crate::fft_plans::plans(64);
+    }
+}
@@ -1,18 +1,178 @@
 use crate::ring_buffer::RingBuffer;
+use crate::spectral_processor::{FrameCtx, SpectralProcessor};
+use crate::decay_processor::DecayProcessor;
+use crate::window::WindowKind;
+use crate::grain_table::GrainTable;
+use crate::fft_sizes;
+use crate::onset::SpectralFlux;
+use crate::limiter::Limiter;
+use crate::lfo::{Lfo, LfoParameters};
+use crate::spectrum_tap::{self, SpectrumTap, SpectrumWriter};
+use crate::meter::{Meter, Meters};
+use crate::denormal;
+use crate::spectral_delay::MAX_DELAY_FRAMES;
+use crate::cepstrum::Cepstrum;
+use crate::phase_vocoder::PhaseVocoder;
+use crate::spectral_print::SpectralPrint;
+use crate::noise_profile::NoiseProfile;
+use crate::rng::{new_rng, seeded_rng};
+use crate::fft_plans;
 use rustfft::num_complex::Complex;
 use rustfft::num_traits::Zero;
 use realfft::{ComplexToReal, RealToComplex};
 use random_fast_rng::{FastRng, Random};
-use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Overlap {
+    Half,
+    ThreeQuarters,
+    SevenEighths,
+}
+
+impl Overlap {
+    // number of grains covering each sample
+    pub fn factor(self) -> usize {
+        match self {
+            Overlap::Half => 2,
+            Overlap::ThreeQuarters => 4,
+            Overlap::SevenEighths => 8,
+        }
+    }
+}
+
+// what happens to the phase of every bin that isn't glitched
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PhaseMode {
+    // keep it, plus fuzz
+    Normal,
+    // zero it, ignoring fuzz
+    Robot,
+    // randomize it completely every frame, ignoring fuzz
+    Whisper,
+}
+
+// which part of the signal gets processed, the rest passes through
+// separated by median filtering, harmonic is what's steady over time and percussive is what's broadband
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Component {
+    Both,
+    Harmonic,
+    Percussive,
+}
+
+#[derive(Copy, Clone, PartialEq)]
 pub struct SpectralDecayParameters {
     pub grain_select: f32,
     pub fuzz: f32,
+    // fuzz scales with (bin / nyquist bin) to this power, 0 is flat and higher keeps more of the lows coherent
+    pub fuzz_slope: f32,
     pub loss: f32,
     pub glitch_freq: f32,
     pub glitch_gain: f32,
+    // -1..1, moves glitches towards the lows or the highs, keeping how many there are on average
+    pub glitch_tilt: f32,
+    // glitched bins freeze and hold for a random number of frames up to this, instead of popping for one
+    pub glitch_hold: f32,
+    // glitched bins fade back from their gain over about this many frames, instead of dropping back after one
+    pub glitch_decay: f32,
     pub delay_select: f32,
+    // picks the synthesis window from the grain sizes like grain_select, centered in the grain when it's shorter,
+    // for the frequency resolution of the grain with a tighter time response, kept at least two hops long
+    pub synthesis_select: f32,
+    pub freeze: bool,
+    // 0..1, how fast a frozen spectrum falls apart, its magnitudes decaying and phases wandering off
+    pub melt: f32,
+    // every bin holds the loudest it's been and keeps being resynthesized, building up a drone until clear_accumulation()
+    pub accumulate: bool,
+    // resynthesize only up to this many of the loudest spectral peaks, tracked across frames as partials, off at 0
+    pub partials: f32,
+    // cents each new partial is detuned by, up to this either way
+    pub partial_detune: f32,
+    // 0..1, fraction of its level a partial loses every frame it lasts, some faster and some slower than others
+    pub partial_decay: f32,
+    pub mix: f32,
+    // transposition in semitones
+    pub pitch: f32,
+    // stretches the frequency axis, each bin moves to this many times its frequency, off at 1
+    pub spread: f32,
+    // moves the spectral envelope by this many semitones, leaving the partials under it in place
+    pub formant: f32,
+    // -1..1, positive fades out the partials leaving the spectral envelope, negative flattens the envelope leaving the partials
+    pub lifter: f32,
+    pub overlap: Overlap,
+    // how many frames bins take to fade in and out of the loss gate, 0 is instant
+    pub gate_attack: f32,
+    pub gate_release: f32,
+    // frames the highest bin is delayed by, lower bins proportionally less
+    pub spectral_delay: f32,
+    pub spectral_delay_feedback: f32,
+    // 0..1, how much of each bin's magnitude carries over from the previous frame
+    pub blur: f32,
+    // 0..1, how much of each bin's peak magnitude is held over to the next frame
+    pub decay: f32,
+    // how much of the sidechain's magnitude spectrum is subtracted from the input's
+    pub duck: f32,
+    // 0..1, cross-synthesis: magnitudes blend from the input's to the sidechain's, phases stay the input's
+    pub morph: f32,
+    // 0..1, how much the input is shaped by the sidechain's spectral envelope, like a channel vocoder
+    pub vocode: f32,
+    // 0..1, how much every frame is filtered by the spectral print, when there is one
+    pub print: f32,
+    // 0..4, spectral subtraction of the noise profile, when there is one, scaled by this over-subtraction factor
+    pub denoise: f32,
+    // 0..1, the least gain denoising leaves a bin with, higher is less musical noise but more residue
+    pub denoise_floor: f32,
+    // only glitch on frames following trigger_glitch(), e.g. on a tempo grid
+    pub glitch_synced: bool,
+    pub phase_mode: PhaseMode,
+    pub component: Component,
+    // per-bin compression above the frame's max amplitude plus this many dB (so 0 or less), off at a ratio of 1
+    pub compress_threshold: f32,
+    pub compress_ratio: f32,
+    // in frames, like gate_attack and gate_release
+    pub compress_attack: f32,
+    pub compress_release: f32,
+    // dB per octave, pivoting around tilt_center in Hz
+    pub tilt: f32,
+    pub tilt_center: f32,
+    // chance of each bin being swapped with another up to scramble_range bins away, every frame
+    pub scramble: f32,
+    pub scramble_range: f32,
+    // number of levels bin magnitudes are quantized to, 0 is off
+    pub crush: f32,
+    // spread the levels evenly in dB instead of amplitude
+    pub crush_db: bool,
+    // in Hz, glitch, loss, fuzz, crush and the phase mode only apply to bins in between
+    pub low_cut: f32,
+    pub high_cut: f32,
+    // 0..1, how much the loss threshold follows the material, from 0 on onsets to double on steady sounds
+    pub adaptive_loss: f32,
+    // keep only the bins loss would have thrown away, the residue, with loss still setting how much is lost
+    pub loss_invert: bool,
+    // compare bins against an absolute level instead of the loudest bin, loss 0..1 going from LOSS_DB_RANGE below full scale up to it
+    pub loss_db: bool,
+    // 0..1, how much of the unprocessed grain is let through on onsets
+    pub preserve_transients: f32,
+    // soft knee limiting of the output to ceiling, in dB
+    pub limiter: bool,
+    pub ceiling: f32,
+    // zero DC and fade in the bins below DC_BLOCK_HZ, after all processing
+    pub dc_block: bool,
+    // scale down every grain that peaks over full scale by its peak, otherwise grains add up to exactly unity gain
+    pub auto_gain: bool,
+    // output time over input time, with the analysis falling behind the input when it's above 1 and catching up below,
+    // up to the max lag, where it jumps back to the input (see set_max_lag)
+    pub stretch: f32,
+    // 0..1, how far back each grain is read from at random, up to JITTER_HOPS hops, for granular stutter and smear
+    pub jitter: f32,
+    // 0..1, chance of each grain being played backwards, for reversed swells of anything that decays
+    pub reverse: f32,
+    // crossfade over BYPASS_SECONDS to the dry signal, delayed to line up with the wet one unlike a host's bypass
+    pub bypass: bool,
+    // applied every frame on top of everything above
+    pub lfo: [LfoParameters; 2],
 }
 
 impl Default for SpectralDecayParameters {
@@ -20,57 +180,660 @@ impl Default for SpectralDecayParameters {
         Self {
             grain_select: 0.,
             fuzz: 0.,
+            fuzz_slope: 0.,
             loss: 0.,
             glitch_freq: 0.,
             glitch_gain: 1.,
-            delay_select: 0.
+            glitch_tilt: 0.,
+            glitch_hold: 0.,
+            glitch_decay: 0.,
+            delay_select: 0.,
+            synthesis_select: 1.,
+            freeze: false,
+            melt: 0.,
+            accumulate: false,
+            partials: 0.,
+            partial_detune: 0.,
+            partial_decay: 0.,
+            mix: 1.,
+            pitch: 0.,
+            spread: 1.,
+            formant: 0.,
+            lifter: 0.,
+            overlap: Overlap::ThreeQuarters,
+            gate_attack: 0.,
+            gate_release: 0.,
+            spectral_delay: 0.,
+            spectral_delay_feedback: 0.,
+            blur: 0.,
+            decay: 0.,
+            duck: 0.,
+            morph: 0.,
+            vocode: 0.,
+            print: 0.,
+            denoise: 0.,
+            denoise_floor: 0.1,
+            glitch_synced: false,
+            phase_mode: PhaseMode::Normal,
+            component: Component::Both,
+            compress_threshold: 0.,
+            compress_ratio: 1.,
+            compress_attack: 0.,
+            compress_release: 0.,
+            tilt: 0.,
+            tilt_center: 1000.,
+            scramble: 0.,
+            scramble_range: 1.,
+            crush: 0.,
+            crush_db: false,
+            low_cut: 0.,
+            high_cut: f32::INFINITY,
+            adaptive_loss: 0.,
+            loss_invert: false,
+            loss_db: false,
+            preserve_transients: 0.,
+            limiter: false,
+            ceiling: 0.,
+            dc_block: false,
+            auto_gain: true,
+            stretch: 1.,
+            jitter: 0.,
+            reverse: 0.,
+            bypass: false,
+            lfo: Default::default()
+        }
+    }
+}
+
+// inclusive ranges randomize() picks from, each inside the parameter's own range
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RandomizeConstraints {
+    pub fuzz: (f32, f32),
+    pub loss: (f32, f32),
+    pub glitch_freq: (f32, f32),
+    // picked exponentially, so both ends have to be above 0
+    pub glitch_gain: (f32, f32),
+}
+
+impl Default for RandomizeConstraints {
+    fn default() -> Self {
+        Self {
+            fuzz: (0., 1.),
+            loss: (0., 1.),
+            glitch_freq: (0., 1.),
+            glitch_gain: (1., 100.),
+        }
+    }
+}
+
+impl SpectralDecayParameters {
+    // new fuzz, loss and glitch settings, everything else is left alone
+    pub fn randomize(&mut self, rng: &mut impl Random, constraints: &RandomizeConstraints) {
+        let mut rand = || rng.gen::<u32>() as f32 / u32::MAX as f32;
+        let mut pick = |(lo, hi): (f32, f32)| lo + (hi - lo) * rand();
+
+        self.fuzz = pick(constraints.fuzz);
+        self.loss = pick(constraints.loss);
+        self.glitch_freq = pick(constraints.glitch_freq);
+        let (lo, hi) = constraints.glitch_gain;
+        self.glitch_gain = lo * (hi / lo).powf(pick((0., 1.)));
+    }
+
+    // continuous parameters ramp, everything else switches to the target right away
+    fn interpolate(&self, target: &Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            fuzz: lerp(self.fuzz, target.fuzz),
+            fuzz_slope: lerp(self.fuzz_slope, target.fuzz_slope),
+            loss: lerp(self.loss, target.loss),
+            glitch_freq: lerp(self.glitch_freq, target.glitch_freq),
+            glitch_gain: lerp(self.glitch_gain, target.glitch_gain),
+            glitch_tilt: lerp(self.glitch_tilt, target.glitch_tilt),
+            melt: lerp(self.melt, target.melt),
+            partial_detune: lerp(self.partial_detune, target.partial_detune),
+            partial_decay: lerp(self.partial_decay, target.partial_decay),
+            mix: lerp(self.mix, target.mix),
+            pitch: lerp(self.pitch, target.pitch),
+            spread: lerp(self.spread, target.spread),
+            formant: lerp(self.formant, target.formant),
+            lifter: lerp(self.lifter, target.lifter),
+            spectral_delay_feedback: lerp(self.spectral_delay_feedback, target.spectral_delay_feedback),
+            blur: lerp(self.blur, target.blur),
+            decay: lerp(self.decay, target.decay),
+            duck: lerp(self.duck, target.duck),
+            morph: lerp(self.morph, target.morph),
+            vocode: lerp(self.vocode, target.vocode),
+            print: lerp(self.print, target.print),
+            denoise: lerp(self.denoise, target.denoise),
+            denoise_floor: lerp(self.denoise_floor, target.denoise_floor),
+            compress_threshold: lerp(self.compress_threshold, target.compress_threshold),
+            compress_ratio: lerp(self.compress_ratio, target.compress_ratio),
+            tilt: lerp(self.tilt, target.tilt),
+            scramble: lerp(self.scramble, target.scramble),
+            adaptive_loss: lerp(self.adaptive_loss, target.adaptive_loss),
+            preserve_transients: lerp(self.preserve_transients, target.preserve_transients),
+            stretch: lerp(self.stretch, target.stretch),
+            jitter: lerp(self.jitter, target.jitter),
+            reverse: lerp(self.reverse, target.reverse),
+            ..*target
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpectralDecayError {
+    NoGrainSizes,
+    // grain sizes have to be a nonzero multiple of 4
+    InvalidGrainSize(usize),
+    UnsortedGrainSizes,
+    // swapped in grains, and their FFTs when zero padded, have to fit the buffers allocated for the grains it was constructed with
+    GrainSizeTooLarge { size: usize, capacity: usize },
+}
+
+impl fmt::Display for SpectralDecayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpectralDecayError::NoGrainSizes => write!(f, "no grain sizes"),
+            SpectralDecayError::InvalidGrainSize(n) => write!(f, "grain size {} is not a nonzero multiple of 4", n),
+            SpectralDecayError::UnsortedGrainSizes => write!(f, "grain sizes are not in ascending order"),
+            SpectralDecayError::GrainSizeTooLarge { size, capacity } => write!(f, "grain size {} is larger than the {} this was constructed with", size, capacity),
+        }
+    }
+}
+
+impl std::error::Error for SpectralDecayError {}
+
+// how long continuous parameters take to reach a new value
+const SMOOTHING_SECONDS: f32 = 0.02;
+
+// input history for the analysis to fall behind into when stretching, in longest grains
+const MAX_LAG_GRAINS: usize = 8;
+
+// furthest back a grain is read from at full jitter, in hops
+const JITTER_HOPS: f32 = 4.;
+
+// frames of silence in and out before frames stop being processed, longer than anything the processor
+// can hold back before it comes out (spectral delay), so stopping doesn't cut off a tail
+const SILENT_FRAMES: usize = MAX_DELAY_FRAMES + 1;
+
+// how long bypass takes to fade in and out
+const BYPASS_SECONDS: f32 = 0.02;
+
+// samples covered by less window than this (like right after a reset) aren't renormalized
+const MIN_WEIGHT: f32 = 0.02;
+
+// spectral flux above this counts as an onset for preserve_transients
+const ONSET_FLUX: f32 = 0.3;
+
+// bins below this get faded in by dc_block, DC is silenced
+const DC_BLOCK_HZ: f32 = 20.;
+
+// quefrencies below this are the spectral envelope, the rest is the fine structure of the partials
+// short enough to stay under the period of most pitched sounds
+const ENVELOPE_SECONDS: f32 = 0.0015;
+// the most reshaping the envelope can raise a bin, in natural log units (about 24dB),
+// so moving a peak over the noise floor or flattening the envelope doesn't blow the noise up
+const ENVELOPE_MAX_BOOST: f32 = 2.77;
+
+// render() processes at most this many samples at a time, and reports progress after each
+const RENDER_CHUNK: usize = 4096;
+
+// a grain table with its FFT plans, built wherever allocating and locking are fine and handed to SpectralDecay::swap_grains
+// the plans come from the process-wide cache, so channels and instances with the same sizes share them
+pub struct GrainPlans {
+    table: Arc<GrainTable>,
+    plans: Vec<(Arc<dyn RealToComplex<f32>>, Arc<dyn ComplexToReal<f32>>)>,
+    // one scratch buffer for every plan, as long as the longest one needs
+    scratch: Vec<Complex<f32>>,
+    // FFT size over grain size, 2 when zero padded
+    padding: usize,
+}
+
+impl GrainPlans {
+    pub fn new(table: Arc<GrainTable>) -> Result<Self, SpectralDecayError> {
+        Self::with_padding(table, 1)
+    }
+
+    // grains are zero padded to twice their length before the FFT and cropped after it,
+    // interpolating the spectrum for finer fuzz and scramble, and leaving room for what heavy edits smear
+    // around the grain instead of wrapping it around onto itself, for twice the FFT work
+    pub fn zero_padded(table: Arc<GrainTable>) -> Result<Self, SpectralDecayError> {
+        Self::with_padding(table, 2)
+    }
+
+    fn with_padding(table: Arc<GrainTable>, padding: usize) -> Result<Self, SpectralDecayError> {
+        let plans: Vec<_> = table.sizes().iter().map(|&n| fft_plans::plans(n * padding)).collect();
+        let scratch_len = plans.iter().map(|(fft, ifft)| fft.get_scratch_len().max(ifft.get_scratch_len())).max().unwrap_or(0);
+
+        Ok(Self { table, plans, scratch: vec![Complex::zero(); scratch_len], padding })
+    }
+
+    pub fn table(&self) -> &Arc<GrainTable> {
+        &self.table
+    }
+
+    pub fn is_zero_padded(&self) -> bool {
+        self.padding > 1
+    }
+
+    pub fn max_fft_size(&self) -> usize {
+        self.table.max_size() * self.padding
+    }
+}
+
+// working space for one frame at a time, nothing in it carries over to the next one,
+// so instances that process one after another on the same thread can take turns with a single set,
+// see SpectralDecay::lend_frame_buffers
+pub(crate) struct FrameBuffers {
+    time_buf: Vec<f32>,
+    freq_buf: Vec<Complex<f32>>,
+    // analysis of the sidechain input, when there is one
+    sc_freq_buf: Vec<Complex<f32>>,
+    // unprocessed spectrum, for crossfading back to on transients
+    dry_freq_buf: Vec<Complex<f32>>,
+    // the synthesis window placed in the middle of a grain, when it's shorter
+    synthesis_buf: Vec<f32>,
+    weight_chunk: Vec<f32>,
+    // a copy of the input for process_in_place
+    in_place_buf: Vec<f32>,
+    cepstrum: Cepstrum,
+}
+
+impl FrameBuffers {
+    fn new(max_grain_size: usize, max_fft_size: usize) -> Self {
+        Self {
+            time_buf: vec![0.; max_fft_size],
+            freq_buf: vec![Complex::zero(); max_fft_size / 2 + 1],
+            sc_freq_buf: vec![Complex::zero(); max_fft_size / 2 + 1],
+            dry_freq_buf: vec![Complex::zero(); max_fft_size / 2 + 1],
+            synthesis_buf: vec![0.; max_grain_size],
+            weight_chunk: vec![0.; max_grain_size],
+            in_place_buf: vec![0.; max_grain_size],
+            cepstrum: Cepstrum::new(max_fft_size),
         }
     }
 }
 
-pub struct SpectralDecay {
+pub struct SpectralDecay<P: SpectralProcessor = DecayProcessor> {
     grain_index: usize,
     grain_size: usize,
+    // grain index of the synthesis window, at most grain_index
+    synthesis_index: usize,
     hop: usize,
     delay_comp: usize,
+    // delay() as of the last latency_changed()
+    reported_delay: usize,
     offset: usize,
-    grains: Vec<(Vec<f32>, RealToComplex<f32>, ComplexToReal<f32>)>,
+    // samples since the last frame, the hop except right after the hop changes
+    since_frame: usize,
+    table: Arc<GrainTable>,
+    plans: Vec<(Arc<dyn RealToComplex<f32>>, Arc<dyn ComplexToReal<f32>>)>,
+    fft_scratch: Vec<Complex<f32>>,
+    padding: usize,
+    fft_size: usize,
+    // the largest grain size the buffers have room for, and its FFT size as constructed
+    capacity: usize,
+    fft_capacity: usize,
+    buffers: FrameBuffers,
+    // the latest grain, plus however far the analysis can lag behind once stretch or jitter is used
     in_buf: RingBuffer<f32>,
+    max_lag: usize,
+    // how far behind the input the analysis is, and the whole samples that came to on the last frame
+    lag: f32,
+    read_lag: usize,
+    // how much further back than the lag the last grain was read from
+    read_jitter: usize,
+    // for jitter and reverse, drawing the same amount every frame so linked channels stay in step
+    grain_rng: FastRng,
+    stretcher: PhaseVocoder,
+    stretching: bool,
     out_buf: RingBuffer<f32>,
-    time_buf: Vec<f32>,
-    freq_buf: Vec<Complex<f32>>,
-    rng: FastRng,
-    params: SpectralDecayParameters
+    // how much window each sample of out_buf got, relative to a steady stream of the current grain size
+    weight_buf: RingBuffer<f32>,
+    // input history long enough to read the dry signal at the max delay
+    dry_buf: RingBuffer<f32>,
+    // the sidechain input, when there is one
+    sc_buf: RingBuffer<f32>,
+    has_sidechain: bool,
+    // grain the onset detector's history belongs to, None when not preserving transients
+    flux_grain: Option<usize>,
+    flux: SpectralFlux,
+    onset_frames: usize,
+    limiter: Limiter,
+    lfos: [Lfo; 2],
+    // where processed spectra go, once someone asked for them
+    tap: Option<SpectrumWriter>,
+    input_meter: Meter,
+    output_meter: Meter,
+    meters: Arc<Meters>,
+    glitch_trigger: bool,
+    accumulate_clear: bool,
+    print: Option<SpectralPrint>,
+    // summed input magnitudes while capturing a print, and the grain size they're from
+    print_sum: Vec<f32>,
+    capturing: Option<usize>,
+    noise_profile: Option<NoiseProfile>,
+    // the same for learning a noise profile, plus how many frames went in
+    noise_sum: Vec<f32>,
+    noise_frames: usize,
+    learning: Option<usize>,
+    sample_rate: f32,
+    processor: P,
+    params: SpectralDecayParameters,
+    // what frames are processed with: ramped from ramp_from to params, then modulated
+    frame_params: SpectralDecayParameters,
+    ramped: SpectralDecayParameters,
+    ramp_from: SpectralDecayParameters,
+    ramp_frame: usize,
+    ramp_frames: usize,
+    // no audio since construction or reset, so there's nothing to ramp from
+    fresh: bool,
+    // 0 is processed, 1 is bypassed
+    bypass_fade: f32,
+    // frames in a row that were silent going in and coming out
+    silent_frames: usize,
+    // what every frame's random numbers are drawn from, None for a sequence of its own
+    seed: Option<u64>,
+    // frames since the last reset, so each one gets a different seed
+    frame: u64,
 }
 
 impl SpectralDecay {
     pub fn new(grain_sizes: &[usize]) -> Self {
-        assert!(grain_sizes.len() > 0);
-        assert!(grain_sizes.iter().all(|n| n % 4 == 0));
-        assert!(grain_sizes.windows(2).all(|n| n[0] <= n[1])); // allow duplicate grain sizes for even spacing
-        let n_max = *grain_sizes.last().unwrap();
-        Self {
+        Self::try_new(grain_sizes).unwrap()
+    }
+
+    pub fn with_window(grain_sizes: &[usize], window_kind: WindowKind) -> Self {
+        Self::try_with_window(grain_sizes, window_kind).unwrap()
+    }
+
+    pub fn try_new(grain_sizes: &[usize]) -> Result<Self, SpectralDecayError> {
+        Self::try_with_window(grain_sizes, WindowKind::Hann)
+    }
+
+    pub fn try_with_window(grain_sizes: &[usize], window_kind: WindowKind) -> Result<Self, SpectralDecayError> {
+        Self::try_with_table(Arc::new(GrainTable::new(grain_sizes, window_kind)?))
+    }
+
+    pub fn with_table(table: Arc<GrainTable>) -> Self {
+        Self::try_with_table(table).unwrap()
+    }
+
+    pub fn try_with_table(table: Arc<GrainTable>) -> Result<Self, SpectralDecayError> {
+        let processor = DecayProcessor::new(table.max_size());
+
+        Self::try_with_table_and_processor(table, processor)
+    }
+
+    pub fn with_plans(grains: GrainPlans) -> Self {
+        let processor = DecayProcessor::new(grains.max_fft_size());
+
+        Self::with_plans_and_processor(grains, processor)
+    }
+}
+
+impl<P: SpectralProcessor> SpectralDecay<P> {
+    pub fn with_processor(grain_sizes: &[usize], window_kind: WindowKind, processor: P) -> Self {
+        Self::try_with_processor(grain_sizes, window_kind, processor).unwrap()
+    }
+
+    pub fn try_with_processor(grain_sizes: &[usize], window_kind: WindowKind, processor: P) -> Result<Self, SpectralDecayError> {
+        Self::try_with_table_and_processor(Arc::new(GrainTable::new(grain_sizes, window_kind)?), processor)
+    }
+
+    pub fn try_with_table_and_processor(table: Arc<GrainTable>, processor: P) -> Result<Self, SpectralDecayError> {
+        Ok(Self::with_plans_and_processor(GrainPlans::new(table)?, processor))
+    }
+
+    // the processor has to take spectra of grains.max_fft_size()
+    pub fn with_plans_and_processor(grains: GrainPlans, processor: P) -> Self {
+        let GrainPlans { table, plans, scratch, padding } = grains;
+        let grain_sizes = table.sizes();
+        let n_max = table.max_size();
+        let fft_max = n_max * padding;
+
+        let mut sd = Self {
             grain_index: 0,
             grain_size: grain_sizes[0],
-            hop: grain_sizes[0] / 4,
-            delay_comp: grain_sizes[0] * 5 / 4,
+            synthesis_index: 0,
+            hop: fft_sizes::hop(grain_sizes[0], Overlap::ThreeQuarters),
+            delay_comp: fft_sizes::latency(grain_sizes[0], Overlap::ThreeQuarters),
+            reported_delay: 0,
             offset: 0,
-            grains: grain_sizes.iter().map(|&n| (
-                (0..n).map(|x| 0.5 - 0.5 * (x as f32 * 2. * PI / n as f32).cos()).collect(),
-                RealToComplex::<f32>::new(n).unwrap(),
-                ComplexToReal::<f32>::new(n).unwrap()
-            )).collect(),
+            since_frame: 0,
+            plans,
+            fft_scratch: scratch,
+            padding,
+            fft_size: grain_sizes[0] * padding,
+            capacity: n_max,
+            fft_capacity: fft_max,
+            buffers: FrameBuffers::new(n_max, fft_max),
             in_buf: RingBuffer::new(n_max, true),
-            out_buf: RingBuffer::new(n_max / 4 * 5, true),
-            time_buf: vec![0.; n_max],
-            freq_buf: vec![Complex::zero(); n_max / 2 + 1],
-            rng: FastRng::new(),
-            params: Default::default()
+            max_lag: n_max * MAX_LAG_GRAINS,
+            lag: 0.,
+            read_lag: 0,
+            read_jitter: 0,
+            grain_rng: new_rng(),
+            stretcher: PhaseVocoder::new(fft_max / 2 + 1),
+            stretching: false,
+            // sized for the longest delay, at 50% overlap
+            out_buf: RingBuffer::new(n_max / 2 * 3, true),
+            weight_buf: RingBuffer::new(n_max / 2 * 3, true),
+            dry_buf: RingBuffer::new(n_max * 2, true),
+            sc_buf: RingBuffer::new(n_max, true),
+            has_sidechain: false,
+            flux_grain: None,
+            flux: SpectralFlux::new(fft_max / 2 + 1),
+            onset_frames: 0,
+            limiter: Limiter::new(),
+            lfos: [Lfo::new(), Lfo::new()],
+            tap: None,
+            input_meter: Meter::default(),
+            output_meter: Meter::default(),
+            meters: Arc::new(Meters::default()),
+            glitch_trigger: false,
+            accumulate_clear: false,
+            print: None,
+            print_sum: vec![0.; fft_max / 2 + 1],
+            capturing: None,
+            noise_profile: None,
+            noise_sum: vec![0.; fft_max / 2 + 1],
+            noise_frames: 0,
+            learning: None,
+            sample_rate: 44100.,
+            processor,
+            params: Default::default(),
+            frame_params: Default::default(),
+            ramped: Default::default(),
+            ramp_from: Default::default(),
+            ramp_frame: 0,
+            ramp_frames: 0,
+            fresh: true,
+            bypass_fade: 0.,
+            silent_frames: 0,
+            seed: None,
+            frame: 0,
+            table
+        };
+        sd.reported_delay = sd.delay();
+        sd
+    }
+
+    pub fn processor(&self) -> &P {
+        &self.processor
+    }
+
+    pub fn processor_mut(&mut self) -> &mut P {
+        &mut self.processor
+    }
+
+    // silence the buffers and forget per-bin state, e.g. when playback stops
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.since_frame = 0;
+        self.in_buf.clear_fill();
+        self.out_buf.clear_fill();
+        self.weight_buf.clear_fill();
+        self.dry_buf.clear_fill();
+        self.sc_buf.clear_fill();
+        self.glitch_trigger = false;
+        self.accumulate_clear = false;
+        self.flux_grain = None;
+        self.onset_frames = 0;
+        self.limiter.reset();
+        self.input_meter.reset();
+        self.output_meter.reset();
+        self.meters.store(Default::default(), Default::default());
+        for lfo in self.lfos.iter_mut() {
+            lfo.reset();
+        }
+        self.frame_params = self.params;
+        self.ramped = self.params;
+        self.ramp_frames = 0;
+        self.fresh = true;
+        self.frame = 0;
+        self.silent_frames = 0;
+        self.lag = 0.;
+        self.read_lag = 0;
+        self.read_jitter = 0;
+        self.stretching = false;
+        // no crossfade to finish, and captures only average what comes after
+        self.bypass_fade = if self.params.bypass { 1. } else { 0. };
+        if self.capturing.is_some() {
+            self.start_print_capture();
+        }
+        if self.learning.is_some() {
+            self.start_noise_learning();
+        }
+        self.processor.reset();
+    }
+
+    // instances with the same seed that get the same calls draw the same random numbers,
+    // so e.g. two channels fuzz and glitch alike and keep a stable image
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        if seed == self.seed {
+            return;
+        }
+        // the lfos draw once per cycle no matter the input, so they only have to start out in step
+        // unlinking has to move the processor off the shared sequence it was last reseeded with
+        match seed {
+            Some(seed) => {
+                for (i, lfo) in self.lfos.iter_mut().enumerate() {
+                    lfo.reseed(seed.wrapping_add(i as u64 + 1));
+                }
+            }
+            None => self.processor.reseed(new_rng().gen::<u64>()),
+        }
+        self.grain_rng = match seed {
+            // the lfos have the two before it
+            Some(seed) => seeded_rng(seed.wrapping_add(3)),
+            None => new_rng(),
+        };
+        self.seed = seed;
+    }
+
+    // how far the analysis can fall behind the input when stretching, MAX_LAG_GRAINS of the longest grain unless set
+    // allocates the history right away instead of the first time stretch or jitter is turned up, and forgets the input
+    pub fn set_max_lag(&mut self, samples: usize) {
+        self.max_lag = samples;
+        self.in_buf = RingBuffer::new(self.capacity + samples, true);
+        self.lag = 0.;
+        self.read_lag = 0;
+        self.read_jitter = 0;
+    }
+
+    // true while silent input is skipping the STFT, see SILENT_FRAMES
+    pub fn is_silent(&self) -> bool {
+        self.silent_frames >= SILENT_FRAMES
+    }
+
+    // only used to map bins to frequencies, 44.1k unless set
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+    }
+
+    // the processed spectrum of every frame from now on, for analyzers
+    // replaces the previous tap if there was one
+    pub fn spectrum_tap(&mut self) -> SpectrumTap {
+        let (writer, tap) = spectrum_tap::spectrum_tap(self.fft_capacity / 2 + 1);
+        self.tap = Some(writer);
+        tap
+    }
+
+    // input and output levels, updated after every process call
+    pub fn meters(&self) -> Arc<Meters> {
+        self.meters.clone()
+    }
+
+    // the next frame is a glitch burst, see glitch_synced
+    pub fn trigger_glitch(&mut self) {
+        self.glitch_trigger = true;
+    }
+
+    // the accumulated spectrum starts over from the next frame, see accumulate
+    pub fn clear_accumulation(&mut self) {
+        self.accumulate_clear = true;
+    }
+
+    // what the print parameter filters by
+    pub fn set_print(&mut self, print: Option<SpectralPrint>) {
+        self.print = print;
+    }
+
+    pub fn print(&self) -> Option<&SpectralPrint> {
+        self.print.as_ref()
+    }
+
+    // average the input of every frame from now until finish_print_capture()
+    pub fn start_print_capture(&mut self) {
+        self.capturing = Some(0);
+        for x in self.print_sum.iter_mut() {
+            *x = 0.;
+        }
+    }
+
+    // replaces the print with what was captured, if anything was
+    // only allocates when there wasn't a print before, or it was smaller
+    pub fn finish_print_capture(&mut self) {
+        if let Some(grain_size) = self.capturing.take() {
+            if grain_size > 0 {
+                let print = self.print.get_or_insert_with(Default::default);
+                print.set_sum(&self.print_sum[..grain_size / 2 + 1]);
+            }
+        }
+    }
+
+    // what the denoise parameter subtracts
+    pub fn set_noise_profile(&mut self, profile: Option<NoiseProfile>) {
+        self.noise_profile = profile;
+    }
+
+    pub fn noise_profile(&self) -> Option<&NoiseProfile> {
+        self.noise_profile.as_ref()
+    }
+
+    // average the input of every frame from now until finish_noise_learning(), which should be only noise
+    pub fn start_noise_learning(&mut self) {
+        self.learning = Some(0);
+        self.noise_frames = 0;
+        for x in self.noise_sum.iter_mut() {
+            *x = 0.;
+        }
+    }
+
+    // replaces the noise profile with what was learned, if anything was
+    // only allocates when there wasn't a profile before, or it was smaller
+    pub fn finish_noise_learning(&mut self) {
+        if let Some(grain_size) = self.learning.take() {
+            if grain_size > 0 {
+                let profile = self.noise_profile.get_or_insert_with(Default::default);
+                profile.set_sum(&self.noise_sum[..grain_size / 2 + 1], self.noise_frames);
+            }
         }
     }
 
     fn select_to_index(&self, select: f32) -> usize {
-        let num_grains = self.grains.len();
+        let num_grains = self.table.sizes().len();
 
         ((select * num_grains as f32) as usize).min(num_grains - 1)
     }
@@ -79,40 +842,291 @@ impl SpectralDecay {
         (self.grain_size + self.hop).max(self.delay_comp)
     }
 
-    pub fn set_params(&mut self, params: SpectralDecayParameters) {
-        if params.grain_select != self.params.grain_select {
-            let grain_index = self.select_to_index(params.grain_select);
-
-            if self.grain_index != grain_index {
-                self.grain_index = grain_index;
-
-                let prev_grain_size = self.grain_size as isize;
-                self.grain_size = self.grains[grain_index].0.len();
-                let grain_size = self.grain_size as isize;
-
-                if (grain_size - prev_grain_size).abs() > grain_size.min(prev_grain_size) {
-                    // differ by more than a factor of 2, reset
-                    self.offset = 0;
-                    self.hop = self.grain_size / 4;
-                } else {
-                    // closer than or equal to a factor of 2, interpolate
-                    let hop_phase = self.offset as f32 / self.hop as f32;
-                    self.hop = self.grain_size / 4;
-                    self.offset = (hop_phase * self.hop as f32) as usize;
-                }
+    // the new delay() if it's changed since construction or the last call, e.g. from grain_select, delay_select or overlap,
+    // for hosts to update their delay compensation, so poll it after set_params
+    pub fn latency_changed(&mut self) -> Option<usize> {
+        let delay = self.delay();
+        if delay == self.reported_delay {
+            None
+        } else {
+            self.reported_delay = delay;
+            Some(delay)
+        }
+    }
+
+    // the largest grain size swap_grains() accepts
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // takes the frame buffers of sds for one set they can share through lend_frame_buffers(), on the same thread
+    // one processed on its own after all allocates a new set
+    pub(crate) fn share_frame_buffers(sds: &mut [Self]) -> FrameBuffers {
+        let capacity = sds.iter().map(|sd| sd.capacity).max().unwrap_or(0);
+        let fft_capacity = sds.iter().map(|sd| sd.fft_capacity).max().unwrap_or(0);
+        for sd in sds.iter_mut() {
+            sd.buffers = FrameBuffers::new(0, 0);
+        }
+        FrameBuffers::new(capacity, fft_capacity)
+    }
+
+    // runs f with buffers in place of its own, they have to be taken from an instance with at least the same capacity
+    pub(crate) fn lend_frame_buffers<R>(&mut self, buffers: &mut FrameBuffers, f: impl FnOnce(&mut Self) -> R) -> R {
+        debug_assert!(buffers.time_buf.len() >= self.fft_capacity && buffers.in_place_buf.len() >= self.capacity);
+        std::mem::swap(&mut self.buffers, buffers);
+        let result = f(self);
+        std::mem::swap(&mut self.buffers, buffers);
+        result
+    }
+
+    // replaces the grain sizes, keeping everything buffered, returning the old ones to be dropped off the audio thread
+    // nothing is allocated, the new grains just can't be longer than capacity()
+    // the grain and delay selects are mapped onto the new sizes
+    pub fn swap_grains(&mut self, grains: GrainPlans) -> Result<GrainPlans, SpectralDecayError> {
+        let size = grains.table.max_size();
+        if size > self.capacity {
+            return Err(SpectralDecayError::GrainSizeTooLarge { size, capacity: self.capacity });
+        }
+        let fft_size = grains.max_fft_size();
+        if fft_size > self.fft_capacity {
+            return Err(SpectralDecayError::GrainSizeTooLarge { size: fft_size, capacity: self.fft_capacity });
+        }
+
+        let old = GrainPlans {
+            table: std::mem::replace(&mut self.table, grains.table),
+            plans: std::mem::replace(&mut self.plans, grains.plans),
+            scratch: std::mem::replace(&mut self.fft_scratch, grains.scratch),
+            padding: std::mem::replace(&mut self.padding, grains.padding),
+        };
+        // grain indices are how the processor tells its state is stale, and they mean different sizes now
+        self.processor.reset();
+        self.flux_grain = None;
+        self.stretching = false;
+        self.select_grain(self.params.grain_select, self.params.overlap);
+        self.select_delay(self.params.delay_select, self.params.overlap);
+        self.select_synthesis(self.params.synthesis_select);
+        Ok(old)
+    }
+
+    // builds the plans for sizes with the current window and padding and swaps them in, which allocates
+    pub fn set_grain_sizes(&mut self, sizes: &[usize]) -> Result<(), SpectralDecayError> {
+        let table = GrainTable::new(sizes, self.table.window_kind())?;
+        self.swap_grains(GrainPlans::with_padding(Arc::new(table), self.padding)?)?;
+        Ok(())
+    }
+
+    // the same for turning zero padding on or off, see GrainPlans::zero_padded
+    // turning it on only fits if it was constructed zero padded, or with grains twice as long
+    pub fn set_zero_padded(&mut self, zero_padded: bool) -> Result<(), SpectralDecayError> {
+        let padding = if zero_padded { 2 } else { 1 };
+        if padding != self.padding {
+            self.swap_grains(GrainPlans::with_padding(self.table.clone(), padding)?)?;
+        }
+        Ok(())
+    }
+
+    fn select_grain(&mut self, grain_select: f32, overlap: Overlap) {
+        let grain_index = self.select_to_index(grain_select);
+        let hop = fft_sizes::hop(self.table.sizes()[grain_index], overlap);
+        self.grain_index = grain_index;
+
+        self.fft_size = self.table.sizes()[grain_index] * self.padding;
+
+        if self.table.sizes()[grain_index] != self.grain_size || self.hop != hop {
+            // phases carried over at another resolution don't fit, at another hop they just move on by a different amount
+            if self.table.sizes()[grain_index] != self.grain_size {
+                self.stretching = false;
+            }
+            let prev_grain_size = self.grain_size as isize;
+            self.grain_size = self.table.sizes()[grain_index];
+            let grain_size = self.grain_size as isize;
+
+            if (grain_size - prev_grain_size).abs() > grain_size.min(prev_grain_size) {
+                // differ by more than a factor of 2, reset
+                self.offset = 0;
+                self.hop = hop;
+            } else {
+                // closer than or equal to a factor of 2, interpolate
+                let hop_phase = self.offset as f32 / self.hop as f32;
+                self.hop = hop;
+                self.offset = (hop_phase * self.hop as f32) as usize;
             }
         }
-        if params.delay_select != self.params.delay_select {
-            let delay_index = self.select_to_index(params.delay_select);
+    }
+
+    // after select_grain, which it depends on
+    fn select_synthesis(&mut self, synthesis_select: f32) {
+        // shorter than two hops would leave gaps between grains
+        let min_index = self.table.sizes().iter().position(|&n| n >= 2 * self.hop).unwrap_or(self.grain_index);
+        self.synthesis_index = self.select_to_index(synthesis_select).max(min_index).min(self.grain_index);
+    }
+
+    fn select_delay(&mut self, delay_select: f32, overlap: Overlap) {
+        let delay_index = self.select_to_index(delay_select);
+        let delay_size = self.table.sizes()[delay_index];
+
+        self.delay_comp = fft_sizes::latency(delay_size, overlap);
+    }
+
+    pub fn set_params(&mut self, params: SpectralDecayParameters) {
+        #[cfg(feature = "rt-check")]
+        let _audio = crate::rt_check::AudioPath::enter("set_params");
+        let overlap = params.overlap;
+
+        if params.grain_select != self.params.grain_select || params.overlap != self.params.overlap {
+            self.select_grain(params.grain_select, overlap);
+        }
+        if params.delay_select != self.params.delay_select || params.overlap != self.params.overlap {
+            self.select_delay(params.delay_select, overlap);
+        }
+        if params.synthesis_select != self.params.synthesis_select || params.grain_select != self.params.grain_select || params.overlap != self.params.overlap {
+            self.select_synthesis(params.synthesis_select);
+        }
 
-            self.delay_comp = self.grains[delay_index].0.len() / 4 * 5;
+        if params != self.params {
+            if self.fresh {
+                self.frame_params = params;
+                self.ramped = params;
+                self.ramp_frames = 0;
+                self.bypass_fade = if params.bypass { 1. } else { 0. };
+            } else {
+                self.ramp_from = self.ramped;
+                self.ramp_frame = 0;
+                self.ramp_frames = (SMOOTHING_SECONDS * self.sample_rate / self.hop as f32).ceil() as usize;
+            }
         }
         self.params = params
     }
 
+    fn advance_ramp(&mut self) {
+        if self.ramp_frame < self.ramp_frames {
+            self.ramp_frame += 1;
+        }
+        self.ramped = if self.ramp_frame < self.ramp_frames {
+            self.ramp_from.interpolate(&self.params, self.ramp_frame as f32 / self.ramp_frames as f32)
+        } else {
+            self.params
+        };
+
+        self.frame_params = self.ramped;
+        let seconds = self.hop as f32 / self.sample_rate;
+        for (lfo, lfo_params) in self.lfos.iter_mut().zip(self.params.lfo.iter()) {
+            if lfo_params.depth > 0. {
+                let value = lfo.advance(lfo_params.shape, lfo_params.rate, seconds);
+                Lfo::modulate(&mut self.frame_params, lfo_params, value);
+            }
+        }
+    }
+
     pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        self.process_inner(input, None, output)
+    }
+
+    // the same as process() with buf as both input and output, without allocating
+    pub fn process_in_place(&mut self, buf: &mut [f32]) {
+        self.own_frame_buffers();
+        // the input is read after the output is written, so each chunk goes through a copy
+        let mut input = std::mem::take(&mut self.buffers.in_place_buf);
+        for chunk in buf.chunks_mut(input.len()) {
+            let input = &mut input[..chunk.len()];
+            input.copy_from_slice(chunk);
+            self.process(input, chunk);
+        }
+        self.buffers.in_place_buf = input;
+    }
+
+    // the sidechain is analyzed alongside the input and handed to the processor
+    pub fn process_sidechain(&mut self, input: &[f32], sidechain: &[f32], output: &mut [f32]) {
+        assert_eq!(input.len(), sidechain.len());
+        self.process_inner(input, Some(sidechain), output)
+    }
+
+    // a whole signal at once, flushing the tail and trimming the latency so the output lines up with the input
+    // params_automation is (sample, params) pairs in time order, each set from that sample of the input on
+    // progress is called with the fraction done, 0..1, after every chunk
+    pub fn render(&mut self, input: &[f32], params_automation: &[(usize, SpectralDecayParameters)], mut progress: impl FnMut(f32)) -> Vec<f32> {
+        let mut points = params_automation.iter().peekable();
+        let mut apply_until = |sd: &mut Self, position: usize| {
+            while let Some(&(_, params)) = points.next_if(|&&(at, _)| at <= position) {
+                sd.set_params(params);
+            }
+            points.peek().map(|&&(at, _)| at)
+        };
+
+        // the latency is whatever the params at the start give
+        apply_until(self, 0);
+        let delay = self.delay();
+
+        let mut input = input.to_vec();
+        input.resize(input.len() + delay, 0.);
+        let mut output = vec![0.; input.len()];
+
+        let mut start = 0;
+        while start < input.len() {
+            let next_point = apply_until(self, start).unwrap_or(input.len());
+            let end = (start + RENDER_CHUNK).min(next_point).min(input.len());
+
+            self.process(&input[start..end], &mut output[start..end]);
+            start = end;
+            progress(start as f32 / input.len() as f32);
+        }
+
+        output.drain(..delay);
+        output
+    }
+
+    // a whole signal stretched to stretch times as long, keeping its pitch, lined up with the input like render()
+    // the analysis falls behind the input for the whole length of it, so this grows the max lag to fit
+    pub fn render_stretched(&mut self, input: &[f32], stretch: f32) -> Vec<f32> {
+        let mut params = self.params;
+        params.stretch = stretch;
+        self.set_params(params);
+        let delay = self.delay();
+
+        let len = (input.len() as f32 * stretch).round() as usize;
+        // sped up, the analysis has to get ahead of the output, so the input goes in that much earlier
+        // with the analysis lagging back to where it will have caught up with the input by then
+        let preroll = input.len().saturating_sub(len);
+        let start_lag = preroll as f32 / stretch;
+        let total = preroll + len + delay;
+        let max_lag = start_lag.max(total as f32 * (1. - 1. / stretch)).ceil() as usize + self.hop;
+        self.set_max_lag(max_lag);
+        self.lag = start_lag;
+        self.read_lag = start_lag.round() as usize;
+        self.stretching = false;
+
+        let mut input = input.to_vec();
+        input.resize(total, 0.);
+        let mut output = vec![0.; total];
+        for (x, y) in input.chunks(RENDER_CHUNK).zip(output.chunks_mut(RENDER_CHUNK)) {
+            self.process(x, y);
+        }
+
+        output.drain(..preroll + delay);
+        output.truncate(len);
+        output
+    }
+
+    // an instance that gave its frame buffers away, and is processed on its own after all, allocates some
+    fn own_frame_buffers(&mut self) {
+        if self.buffers.time_buf.len() < self.fft_capacity {
+            self.buffers = FrameBuffers::new(self.capacity, self.fft_capacity);
+        }
+    }
+
+    fn process_inner(&mut self, input: &[f32], sidechain: Option<&[f32]>, output: &mut [f32]) {
         use std::iter::once;
+        // most never stretch or jitter, so the lag history only grows the first time it's needed,
+        // the one allocation allowed on the audio path, see set_max_lag() to make it up front instead
+        if (self.params.stretch != 1. || self.params.jitter > 0.) && self.in_buf.len() < self.capacity + self.max_lag {
+            self.in_buf.resize(self.capacity + self.max_lag);
+        }
+        #[cfg(feature = "rt-check")]
+        let _audio = crate::rt_check::AudioPath::enter("process");
         assert_eq!(input.len(), output.len());
+        self.own_frame_buffers();
+
+        self.has_sidechain = sidechain.is_some();
 
         let initial_hop = input.len().min(self.hop - self.offset);
 
@@ -122,79 +1136,364 @@ impl SpectralDecay {
         let in_iter = once(in_head).chain(in_tail.chunks(self.hop));
         let out_iter = once(out_head).chain(out_tail.chunks_mut(self.hop));
 
+        let delay = self.delay();
+        self.fresh = false;
+
+        let mut position = 0;
+
         // hop
         for (in_chunk, out_chunk) in in_iter.zip(out_iter) {
             self.in_buf.copy_replace(Some(in_chunk), None);
             self.out_buf.copy_replace(None, Some(out_chunk));
 
+            // grains of different sizes don't overlap-add to a constant where they meet,
+            // so undo whatever gain they do add up to there
+            let weights = &mut self.buffers.weight_chunk[..out_chunk.len()];
+            self.weight_buf.copy_replace(None, Some(weights));
+            for (y, &w) in out_chunk.iter_mut().zip(weights.iter()) {
+                if (w - 1.).abs() > 1e-3 && w > MIN_WEIGHT {
+                    *y /= w;
+                }
+            }
+
+            if let Some(sidechain) = sidechain {
+                self.sc_buf.copy_replace(Some(&sidechain[position..position + in_chunk.len()]), None);
+            }
+            position += in_chunk.len();
+
+            // blend with the input delayed by the same amount as the wet signal
+            self.dry_buf.copy_replace(Some(in_chunk), None);
+            let dry = self.dry_buf.iter(-((delay + in_chunk.len()) as isize));
+            let mix = self.frame_params.mix;
+            for (y, x) in out_chunk.iter_mut().zip(dry) {
+                *y = *y * mix + *x * (1. - mix);
+            }
+            if self.params.limiter {
+                self.limiter.process(out_chunk, self.params.ceiling, self.sample_rate);
+            }
+            if self.params.bypass || self.bypass_fade > 0. {
+                let step = 1. / (BYPASS_SECONDS * self.sample_rate);
+                let dry = self.dry_buf.iter(-((delay + in_chunk.len()) as isize));
+                for (y, x) in out_chunk.iter_mut().zip(dry) {
+                    self.bypass_fade = if self.params.bypass { (self.bypass_fade + step).min(1.) } else { (self.bypass_fade - step).max(0.) };
+                    *y += (*x - *y) * self.bypass_fade;
+                }
+            }
+
             self.offset += in_chunk.len();
+            self.since_frame += in_chunk.len();
 
             if self.offset >= self.hop {
                 self.offset -= self.hop;
                 self.process_buffers();
             }
         }
+
+        // anything non-finite would be overlap-added and fed back indefinitely, so start over
+        if output.iter().any(|x| !x.is_finite()) {
+            self.reset();
+            for y in output.iter_mut() {
+                *y = 0.;
+            }
+        }
+
+        let input_level = self.input_meter.process(input, self.sample_rate);
+        let output_level = self.output_meter.process(output, self.sample_rate);
+        self.meters.store(input_level, output_level);
     }
 
     fn process_buffers(&mut self) {
+        self.advance_ramp();
         let delay = self.delay();
-        let (ref window, ref mut fft, ref mut ifft) = self.grains[self.grain_index];
-        let mut time_buf = &mut self.time_buf[..self.grain_size];
-        let mut freq_buf = &mut self.freq_buf[..self.grain_size / 2 + 1];
-        // window/normalize input
 
-        for ((y, x), w) in time_buf.iter_mut().zip(self.in_buf.iter(-(self.grain_size as isize))).zip(window) {
-            *y = x * 2. * w;
+        // the output moves on by a hop, the analysis by a hop over stretch
+        // it can't get ahead of the input, and jumps back to it once it's too far behind
+        let elapsed = std::mem::take(&mut self.since_frame);
+        let stretch = self.frame_params.stretch.max(0.01);
+        let prev_lag = self.read_lag;
+        self.lag = (self.lag + elapsed as f32 * (1. - 1. / stretch)).max(0.);
+        let jumped = self.lag > (self.in_buf.len() - self.grain_size) as f32;
+        if jumped {
+            self.lag = 0.;
         }
+        self.read_lag = self.lag.round() as usize;
 
-        // to freq domain
-        fft.process(&mut time_buf, &mut freq_buf).unwrap();
+        // jitter reads from further back still, as far as the history goes
+        let prev_jitter = self.read_jitter;
+        let mut rand = || self.grain_rng.gen::<u32>() as f32 / u32::MAX as f32;
+        let (jitter_rand, reverse_rand) = (rand(), rand());
+        let max_jitter = self.in_buf.len() - self.grain_size - self.read_lag;
+        self.read_jitter = ((jitter_rand * self.frame_params.jitter * JITTER_HOPS * self.hop as f32) as usize).min(max_jitter);
 
-        // process spectrum
-        let SpectralDecayParameters {
-            fuzz,
-            loss,
-            glitch_freq,
-            glitch_gain,
-            ..
-        } = self.params;
+        let analysis_hop = (elapsed + prev_lag + prev_jitter) as isize - (self.read_lag + self.read_jitter) as isize;
+        let restart_stretch = jumped || !self.stretching;
+        // jittered grains keep the phases running on too, so they don't cancel where they overlap
+        self.stretching = stretch != 1. || self.read_lag > 0 || self.frame_params.jitter > 0.;
+        let read_start = -((self.grain_size + self.read_lag + self.read_jitter) as isize);
+
+        let window = self.table.window(self.grain_index);
+        let (fft, ifft) = &self.plans[self.grain_index];
+        let scratch = &mut self.fft_scratch[..];
+        let mut time_buf = &mut self.buffers.time_buf[..self.fft_size];
+        let mut freq_buf = &mut self.buffers.freq_buf[..self.fft_size / 2 + 1];
+        // window/normalize input
 
-        let rng = &mut self.rng;
-        let mut rand = || { rng.gen::<u32>() as f32 / u32::MAX as f32 };
-        let mut max_amp = 0.;
+        // at 50% overlap, hann squared doesn't add up to a constant but hann does,
+        // so split it between analysis and synthesis (keeping the sign for windows that go negative)
+        let sqrt_window = self.params.overlap == Overlap::Half;
+        let window_kind = self.table.window_kind();
+        let split = |w: f32| if sqrt_window { w.abs().sqrt().copysign(w) } else { w };
+        let shortened = self.table.sizes()[self.synthesis_index] < self.grain_size;
+        let synthesis = if !shortened {
+            window
+        } else {
+            let short = self.table.window(self.synthesis_index);
+            let start = (self.grain_size - short.len()) / 2;
+            let synthesis = &mut self.buffers.synthesis_buf[..self.grain_size];
+            for x in synthesis.iter_mut() {
+                *x = 0.;
+            }
+            synthesis[start..start + short.len()].copy_from_slice(short);
+            &*synthesis
+        };
+        // overlapping grains sum to this gain
+        let cola = if !shortened {
+            let mean_square = if sqrt_window { window_kind.mean() } else { window_kind.mean_square() };
+            mean_square * self.grain_size as f32 / self.hop as f32
+        } else {
+            // no closed form for two different windows
+            window.iter().zip(synthesis).map(|(&a, &s)| split(a) * split(s)).sum::<f32>() / self.hop as f32
+        };
+        let weight_start = (delay - self.grain_size) as isize;
+        // counts silent frames too, so linked channels reseed in step whichever of them skips
+        let frame = self.frame;
+        self.frame += 1;
 
-        for x in freq_buf.iter() {
-            max_amp = x.norm().max(max_amp);
+        // once silence has gone in and come out for long enough, it only takes silence going in to stay silent,
+        // and any input to wake up, so skip the FFTs but keep the weights that later frames overlap with
+        let silent_input = self.in_buf.iter(read_start).all(|&x| x == 0.)
+            && (!self.has_sidechain || self.sc_buf.iter(-(self.grain_size as isize)).all(|&x| x == 0.))
+            && self.capturing.is_none()
+            && self.learning.is_none();
+        if !silent_input {
+            self.silent_frames = 0;
+        } else if self.silent_frames >= SILENT_FRAMES {
+            self.glitch_trigger = false;
+            self.accumulate_clear = false;
+            add_weights(&mut self.weight_buf, weight_start, window, synthesis, sqrt_window, cola);
+            return;
         }
 
-        for x in freq_buf.iter_mut() {
-            if rand() < glitch_freq / 8. {
-                let k = rand();
-                *x *= k * k * glitch_gain;
-            } else if x.norm() / max_amp < loss {
-                *x = Complex::zero();
-            } else if fuzz > 0. {
-                let (r, theta) = x.to_polar();
-                let delta = 2. * PI * rand();
+        let sidechain = if self.has_sidechain {
+            let sc_freq_buf = &mut self.buffers.sc_freq_buf[..self.fft_size / 2 + 1];
 
-                *x = Complex::from_polar(r, theta + delta * fuzz);
+            for ((y, x), &w) in time_buf.iter_mut().zip(self.sc_buf.iter(-(self.grain_size as isize))).zip(window) {
+                let w = if sqrt_window { w.abs().sqrt().copysign(w) } else { w };
+                *y = x * 2. * w;
             }
-        }
+            for y in time_buf[self.grain_size..].iter_mut() {
+                *y = 0.;
+            }
+            fft.process_with_scratch(&mut time_buf, sc_freq_buf, scratch).unwrap();
 
-        // to time domain
-        ifft.process(&mut freq_buf, &mut time_buf).unwrap();
+            Some(&*sc_freq_buf)
+        } else {
+            None
+        };
 
-        // window/normalize output
-        let mut max_amp = 1.;
-        for (x, w) in time_buf.iter_mut().zip(window) {
-            *x *= w / self.grain_size as f32;
-            max_amp = x.abs().max(max_amp);
+        for ((y, x), &w) in time_buf.iter_mut().zip(self.in_buf.iter(read_start)).zip(window) {
+            let w = if sqrt_window { w.abs().sqrt().copysign(w) } else { w };
+            *y = x * 2. * w;
+        }
+        // the window is symmetric, so this is the same as windowing the reversed input
+        if reverse_rand < self.frame_params.reverse {
+            time_buf[..self.grain_size].reverse();
+        }
+        for y in time_buf[self.grain_size..].iter_mut() {
+            *y = 0.;
+        }
+
+        // to freq domain
+        fft.process_with_scratch(&mut time_buf, &mut freq_buf, scratch).unwrap();
+
+        // frames read further apart or closer together than they're written have to have their phases carried over
+        if self.stretching {
+            self.stretcher.stretch(freq_buf, self.fft_size, analysis_hop, elapsed, restart_stretch);
+        }
+
+        // an FFT size change starts the capture over, averages of different resolutions don't mix
+        if let Some(fft_size) = self.capturing {
+            if fft_size != self.fft_size {
+                self.capturing = Some(self.fft_size);
+                for x in self.print_sum.iter_mut() {
+                    *x = 0.;
+                }
+            }
+            for (y, x) in self.print_sum.iter_mut().zip(freq_buf.iter()) {
+                *y += x.norm();
+            }
+        }
+        if let Some(fft_size) = self.learning {
+            if fft_size != self.fft_size {
+                self.learning = Some(self.fft_size);
+                self.noise_frames = 0;
+                for x in self.noise_sum.iter_mut() {
+                    *x = 0.;
+                }
+            }
+            for (y, x) in self.noise_sum.iter_mut().zip(freq_buf.iter()) {
+                *y += x.norm();
+            }
+            self.noise_frames += 1;
+        }
+
+        // spectral subtraction, on the input so everything downstream works on the cleaned up signal
+        if let (Some(profile), true) = (&self.noise_profile, self.frame_params.denoise > 0.) {
+            let (amount, floor) = (self.frame_params.denoise, self.frame_params.denoise_floor);
+            let num_bins = freq_buf.len();
+            for (k, x) in freq_buf.iter_mut().enumerate() {
+                let r = x.norm();
+                if r > 0. {
+                    *x *= (1. - amount * profile.level(k, num_bins) / r).max(floor);
+                }
+            }
+        }
+
+        // frames with a big enough jump in spectral flux are onsets
+        let preserve = if self.frame_params.preserve_transients == 0. {
+            self.flux_grain = None;
+            self.onset_frames = 0;
+            0.
+        } else {
+            if self.flux_grain != Some(self.grain_index) {
+                self.flux_grain = Some(self.grain_index);
+                self.flux.clear();
+            }
+            // keep preserving every frame the onset is in, not just the first one to see it
+            if self.flux.process(freq_buf) > ONSET_FLUX {
+                self.onset_frames = self.grain_size / self.hop;
+            }
+            if self.onset_frames > 0 {
+                self.onset_frames -= 1;
+                self.frame_params.preserve_transients
+            } else {
+                0.
+            }
+        };
+        let dry_freq_buf = &mut self.buffers.dry_freq_buf[..self.fft_size / 2 + 1];
+        if preserve > 0. {
+            dry_freq_buf.copy_from_slice(freq_buf);
+        }
+
+        // a sine of amplitude 1 comes out of the FFT as half the sum of the window, times the 2 it was scaled up by
+        let full_scale = window.iter().map(|&w| if sqrt_window { w.abs().sqrt() } else { w.abs() }).sum();
+
+        // how many numbers a frame draws depends on its input, so resync every frame
+        if let Some(seed) = self.seed {
+            self.processor.reseed(seed ^ frame.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        }
+
+        // process spectrum
+        self.processor.process(freq_buf, FrameCtx {
+            params: &self.frame_params,
+            grain_index: self.grain_index,
+            grain_size: self.grain_size,
+            fft_size: self.fft_size,
+            hop: self.hop,
+            sample_rate: self.sample_rate,
+            full_scale,
+            sidechain,
+            glitch_trigger: std::mem::take(&mut self.glitch_trigger),
+            accumulate_clear: std::mem::take(&mut self.accumulate_clear),
+        });
+
+        // rebuild each bin's log magnitude from the (shifted) envelope and the fine structure, in the lifter's proportions,
+        // and scale the bin by the difference, keeping its phase
+        let (formant, lifter) = (self.frame_params.formant, self.frame_params.lifter);
+        if formant != 0. || lifter != 0. {
+            let cutoff = (ENVELOPE_SECONDS * self.sample_rate) as usize;
+            self.buffers.cepstrum.analyze(freq_buf, fft.as_ref(), ifft.as_ref(), scratch, cutoff);
+            let ratio = 2f32.powf(formant / 12.);
+            let (envelope_amount, fine_amount) = if lifter > 0. { (1., 1. - lifter) } else { (1. + lifter, 1.) };
+            // a flattened envelope sits at its peak level
+            let peak = self.buffers.cepstrum.envelope().iter().fold(f32::MIN, |max, &x| x.max(max));
+            for (k, x) in freq_buf.iter_mut().enumerate() {
+                let envelope = self.buffers.cepstrum.envelope()[k];
+                let fine = self.buffers.cepstrum.fine()[k];
+                let shifted = if formant != 0. { self.buffers.cepstrum.envelope_at(k as f32 / ratio) } else { envelope };
+                let log = peak + (shifted - peak) * envelope_amount + fine * fine_amount;
+                *x *= (log - envelope - fine).min(ENVELOPE_MAX_BOOST).exp();
+            }
+        }
+
+        if preserve > 0. {
+            for (x, &dry) in freq_buf.iter_mut().zip(dry_freq_buf.iter()) {
+                *x = *x * (1. - preserve) + dry * preserve;
+            }
+        }
+
+        // garbage in the spectrum means the processor's state is likely garbage too
+        if denormal::sanitize(freq_buf) {
+            self.processor.reset();
+        }
+
+        if let (Some(print), true) = (&self.print, self.frame_params.print > 0.) {
+            let amount = self.frame_params.print;
+            let num_bins = freq_buf.len();
+            for (k, x) in freq_buf.iter_mut().enumerate() {
+                *x *= 1. - amount + amount * print.gain(k, num_bins);
+            }
+        }
+
+        // a glitched DC bin is a big offset that eats headroom downstream
+        if self.frame_params.dc_block {
+            let bin_freq = self.sample_rate / self.fft_size as f32;
+            for (k, x) in freq_buf.iter_mut().enumerate().take_while(|&(k, _)| k as f32 * bin_freq < DC_BLOCK_HZ) {
+                *x *= k as f32 * bin_freq / DC_BLOCK_HZ;
+            }
+        }
+
+        if silent_input && freq_buf.iter().all(|x| x.re == 0. && x.im == 0.) {
+            self.silent_frames += 1;
+        } else {
+            self.silent_frames = 0;
+        }
+
+        if let Some(tap) = &mut self.tap {
+            // relative to the window's gain, so a full scale sine peaks at about 1
+            let scale = 1. / (self.grain_size as f32 * self.table.window_kind().mean());
+            tap.publish(self.fft_size, freq_buf.iter().map(|x| x.norm() * scale));
+        }
+
+        // to time domain, DC and nyquist can't have a phase in a real signal
+        freq_buf[0].im = 0.;
+        freq_buf.last_mut().unwrap().im = 0.;
+        ifft.process_with_scratch(&mut freq_buf, &mut time_buf, scratch).unwrap();
+
+        // window/normalize output, cropping off the padding
+        let time_buf = &mut time_buf[..self.grain_size];
+        let mut max_amp = 1.;
+        for (x, &w) in time_buf.iter_mut().zip(synthesis) {
+            *x *= split(w) / self.fft_size as f32;
+            max_amp = x.abs().max(max_amp);
+        }
+        if !self.frame_params.auto_gain {
+            max_amp = 1.;
         }
 
         // overlap add
-        for (y, x) in self.out_buf.iter_mut((delay - self.grain_size) as isize).zip(time_buf) {
-            *y += *x / (max_amp * 1.5);
+        for (y, x) in self.out_buf.iter_mut(weight_start).zip(time_buf.iter()) {
+            *y += *x / (max_amp * cola);
         }
+        add_weights(&mut self.weight_buf, weight_start, window, synthesis, sqrt_window, cola);
+    }
+}
+
+// how much of the windows a frame overlap-adds, for renormalizing where grains don't add up to 1
+fn add_weights(weight_buf: &mut RingBuffer<f32>, start: isize, analysis: &[f32], synthesis: &[f32], sqrt_window: bool, cola: f32) {
+    for (y, (&a, &s)) in weight_buf.iter_mut(start).zip(analysis.iter().zip(synthesis)) {
+        let w2 = if sqrt_window { (a.abs() * s.abs()).sqrt() * (a * s).signum() } else { a * s };
+        *y += w2 / cola;
     }
 }
 
@@ -237,6 +1536,89 @@ mod tests {
         // assert!(false);
     }
 
+    #[test]
+    fn sd_silence() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut output = vec![0.; 100 * n];
+        sd.process(&vec![0.; 100 * n], &mut output);
+        assert!(sd.is_silent());
+        assert!(output.iter().all(|&x| x == 0.));
+
+        // wakes up without a glitch in the gain
+        let input = vec![1.; 4 * n];
+        let mut output = vec![0.; 4 * n];
+        sd.process(&input, &mut output);
+        assert!(!sd.is_silent());
+        for x in &output[2 * n..] {
+            assert!((*x - 1.).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn sd_in_place() {
+        let n = 32;
+        let input: Vec<_> = (0..8 * n).map(|x| (x as f32 * 0.1).sin()).collect();
+        let mut p = SpectralDecayParameters::default();
+        p.loss = 0.5;
+
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.set_params(p);
+        let mut expected = vec![0.; input.len()];
+        sd.process(&input, &mut expected);
+
+        // blocks both shorter and longer than the copy
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.set_params(p);
+        let mut buf = input.clone();
+        sd.process_in_place(&mut buf[..5]);
+        sd.process_in_place(&mut buf[5..]);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn sd_share_frame_buffers() {
+        let n = 32;
+        let input: Vec<_> = (0..8 * n).map(|x| (x as f32 * 0.1).sin()).collect();
+        let mut p = SpectralDecayParameters::default();
+        p.loss = 0.5;
+
+        let mut sd = SpectralDecay::new(&[n, 2 * n]);
+        sd.set_params(p);
+        let mut expected = vec![0.; input.len()];
+        sd.process(&input, &mut expected);
+
+        let mut sds = [SpectralDecay::new(&[n, 2 * n]), SpectralDecay::new(&[n, 2 * n]), SpectralDecay::new(&[n])];
+        let mut buffers = SpectralDecay::share_frame_buffers(&mut sds);
+        let mut outputs = vec![vec![0.; input.len()]; 2];
+        for (sd, output) in sds.iter_mut().zip(outputs.iter_mut()) {
+            sd.set_params(p);
+            sd.lend_frame_buffers(&mut buffers, |sd| sd.process(&input, output));
+        }
+        assert!(outputs.iter().all(|output| *output == expected));
+
+        // and one processed on its own gets a set of its own back
+        let mut buf = input.clone();
+        sds[2].process_in_place(&mut buf);
+        assert!(sds[2].buffers.time_buf.len() >= n);
+    }
+
+    #[test]
+    fn sd_lag_on_demand() {
+        let n = 32;
+        let input = vec![0.; 4 * n];
+        let mut output = vec![0.; 4 * n];
+        let mut sd = SpectralDecay::new(&[n, 2 * n]);
+        sd.process(&input, &mut output);
+        assert_eq!(sd.in_buf.len(), 2 * n);
+
+        let mut p = SpectralDecayParameters::default();
+        p.jitter = 0.5;
+        sd.set_params(p);
+        sd.process(&input, &mut output);
+        assert_eq!(sd.in_buf.len(), 2 * n * (1 + MAX_LAG_GRAINS));
+    }
+
     #[test]
     fn sd_delay() {
         let n = 32;
@@ -282,4 +1664,1374 @@ mod tests {
         assert_eq!(sd.delay(), 64 + 16);
         assert_eq!(index, 64 + 16);
     }
+
+    #[test]
+    fn sd_freeze() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+
+        let input: Vec<_> = (0..4 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+        let mut output = vec![0.; 4 * n];
+        sd.process(&input, &mut output);
+
+        let mut p = SpectralDecayParameters::default();
+        p.freeze = true;
+        sd.set_params(p);
+
+        // input goes silent but the latched spectrum keeps sounding
+        let input = vec![0.; 4 * n];
+        sd.process(&input, &mut output);
+        assert!(output[2 * n..].iter().any(|x| x.abs() > 0.1));
+
+        p.freeze = false;
+        sd.set_params(p);
+
+        sd.process(&input, &mut output);
+        sd.process(&input, &mut output);
+        assert!(output.iter().all(|x| x.abs() < 1e-6));
+    }
+
+    #[test]
+    fn sd_melt() {
+        let n = 32;
+        let run = |melt: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let input: Vec<_> = (0..4 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+            let mut output = vec![0.; 4 * n];
+            sd.process(&input, &mut output);
+
+            let mut p = SpectralDecayParameters::default();
+            p.freeze = true;
+            p.melt = melt;
+            sd.set_params(p);
+            let mut output = vec![0.; 64 * n];
+            sd.process(&vec![0.; 64 * n], &mut output);
+            output[60 * n..].iter().map(|y| y * y).sum::<f32>()
+        };
+
+        // a couple of hundred frames in, a melting freeze has all but faded away, and a plain one hasn't
+        let held = run(0.);
+        assert!(held > 0.1);
+        assert!(run(1.) < held * 0.01);
+    }
+
+    #[test]
+    fn sd_accumulate() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.accumulate = true;
+        sd.set_params(p);
+
+        let tone = |k: f32| (0..n * 4).map(|i| (2. * PI * k * i as f32 / n as f32).cos()).collect::<Vec<_>>();
+        let mut output = vec![0.; n * 4];
+
+        // the first tone is still there once the second one takes over
+        sd.process(&tone(4.), &mut output);
+        sd.process(&tone(12.), &mut output);
+        let (bins, _) = tap.read();
+        assert!(bins[4] > 0.9 && bins[12] > 0.9);
+
+        // and gone once cleared
+        sd.clear_accumulation();
+        sd.process(&tone(12.), &mut output);
+        let (bins, _) = tap.read();
+        assert!(bins[4] < 0.01 && bins[12] > 0.9);
+    }
+
+    #[test]
+    fn sd_partials() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let input: Vec<_> = (0..n * 8).map(|i| {
+            let t = 2. * PI * i as f32 / n as f32;
+            (8. * t).cos() + 0.3 * (20. * t).cos()
+        }).collect();
+        let run = |pitch: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut tap = sd.spectrum_tap();
+            let mut p = SpectralDecayParameters::default();
+            p.partials = 1.;
+            p.pitch = pitch;
+            sd.set_params(p);
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            tap.read().0.to_vec()
+        };
+
+        // only the louder tone is left
+        let bins = run(0.);
+        assert!(bins[8] > 0.9 && bins[20] < 0.01);
+
+        // and moves up an octave whole
+        let bins = run(12.);
+        assert!(bins[16] > 0.9 && bins[8] < 0.01);
+    }
+
+    #[test]
+    fn sd_blur() {
+        let n = 32;
+        let tail = |blur: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.blur = blur;
+            sd.set_params(p);
+
+            let input: Vec<_> = (0..4 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+            let mut output = vec![0.; 4 * n];
+            sd.process(&input, &mut output);
+
+            // energy left after the input goes silent
+            let input = vec![0.; 4 * n];
+            sd.process(&input, &mut output);
+            output[2 * n..].iter().map(|x| x * x).sum::<f32>()
+        };
+
+        assert!(tail(0.) < 1e-6);
+        assert!(tail(0.9) > 0.1);
+    }
+
+    #[test]
+    fn sd_decay() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.decay = 0.9;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..4 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+        let mut output = vec![0.; 4 * n];
+        sd.process(&input, &mut output);
+
+        // the tail rings out after the input goes silent, getting quieter
+        let input = vec![0.; 4 * n];
+        let mut energy = Vec::new();
+        for _ in 0..4 {
+            sd.process(&input, &mut output);
+            energy.push(output.iter().map(|x| x * x).sum::<f32>());
+        }
+        assert!(energy[0] > 0.1);
+        assert!(energy.windows(2).all(|e| e[1] < e[0]));
+    }
+
+    #[test]
+    fn sd_robot() {
+        use std::f32::consts::PI;
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.phase_mode = PhaseMode::Robot;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| (2. * PI * 3. * x as f32 / n as f32).sin()).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input, &mut output);
+
+        // every frame is the same pulse, so the output repeats every hop
+        let hop = n / 4;
+        assert!(output[4 * n..].iter().any(|x| x.abs() > 0.1));
+        for i in 4 * n..7 * n {
+            assert!((output[i] - output[i + hop]).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn sd_whisper() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.phase_mode = PhaseMode::Whisper;
+        p.overlap = Overlap::SevenEighths;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..64 * n).map(|x| 0.1 * (x as f32 * 0.3).sin() + 0.1 * (x as f32 * 1.1).sin()).collect();
+        let mut output = vec![0.; 64 * n];
+        sd.process(&input, &mut output);
+
+        // noise, but about as loud as the input
+        let rms = |x: &[f32]| (x.iter().map(|x| x * x).sum::<f32>() / x.len() as f32).sqrt();
+        let ratio = rms(&output[4 * n..]) / rms(&input);
+        assert!(ratio > 0.7 && ratio < 1.4, "{}", ratio);
+    }
+
+    // magnitude of bin k of a DFT as long as x
+    fn dft_level(x: &[f32], k: usize) -> f32 {
+        let (re, im) = x.iter().enumerate().fold((0., 0.), |(re, im), (i, &y)| {
+            let phase = 2. * std::f32::consts::PI * (k * i) as f32 / x.len() as f32;
+            (re + y * phase.cos(), im + y * phase.sin())
+        });
+        (re * re + im * im).sqrt()
+    }
+
+    #[test]
+    fn sd_glitch_tilt() {
+        let n = 32;
+        // DC and nyquist, the two ends of the spectrum
+        let input: Vec<_> = (0..8 * n).map(|i| 0.5 + if i % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        let run = |glitch_freq: f32, glitch_tilt: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut tap = sd.spectrum_tap();
+            let mut p = SpectralDecayParameters::default();
+            p.glitch_freq = glitch_freq;
+            p.glitch_tilt = glitch_tilt;
+            sd.set_params(p);
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            let (bins, _) = tap.read();
+            (bins[0], bins[n / 2])
+        };
+
+        // glitches tilted all the way to one end never touch the other
+        let (dc, nyquist) = run(0., 0.);
+        assert!((run(1., -1.).1 - nyquist).abs() < 1e-6);
+        assert!((run(1., 1.).0 - dc).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sd_glitch_hold() {
+        let n = 32;
+        let tone: Vec<_> = (0..4 * n).map(|i| (2. * std::f32::consts::PI * 4. * i as f32 / n as f32).sin()).collect();
+        let tail = |glitch_hold: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.glitch_freq = 1.;
+            p.glitch_synced = true;
+            p.glitch_hold = glitch_hold;
+            sd.set_params(p);
+            let mut output = vec![0.; 8 * n];
+            sd.process(&tone, &mut output[..4 * n]);
+            sd.trigger_glitch();
+            sd.process(&tone[..n], &mut output[..n]);
+            sd.process(&vec![0.; 8 * n], &mut output);
+            output[7 * n..].iter().fold(0f32, |max, x| x.abs().max(max))
+        };
+
+        // held bins keep ringing after the input stops
+        assert!(tail(0.) < 1e-6);
+        assert!(tail(1000.) > 1e-6);
+    }
+
+    #[test]
+    fn sd_fuzz_slope() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.fuzz = 1.;
+        p.fuzz_slope = 4.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| {
+            let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+            0.5 * (2. * x).sin()
+        }).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input, &mut output);
+
+        // an eighth of the way up, fuzz is down to 1/4096 and the tone holds together
+        let gain = dft_level(&output[7 * n..], 2) / dft_level(&input[..n], 2);
+        assert!((gain - 1.).abs() < 0.01, "{}", gain);
+    }
+
+    #[test]
+    fn sd_tilt() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.set_sample_rate(32000.);
+        let mut p = SpectralDecayParameters::default();
+        // bin 4 is at 4kHz
+        p.tilt = 6.;
+        p.tilt_center = 4000.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| {
+            let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+            0.1 * (4. * x).sin() + 0.1 * (8. * x).sin()
+        }).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input, &mut output);
+
+        // an octave up is 6dB louder, 4kHz is untouched
+        let center = dft_level(&output[7 * n..], 4) / dft_level(&input[..n], 4);
+        let octave = dft_level(&output[7 * n..], 8) / dft_level(&output[7 * n..], 4);
+        assert!((center - 1.).abs() < 0.01, "{}", center);
+        assert!((octave - 2.).abs() < 0.05, "{}", octave);
+    }
+
+    #[test]
+    fn sd_crush() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.crush = 1.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| {
+            let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+            0.5 * (4. * x).sin() + 0.1 * (11. * x).sin()
+        }).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input, &mut output);
+
+        // with one level, anything under half the loudest bin rounds to nothing
+        let gain = |k| dft_level(&output[7 * n..], k) / dft_level(&input[..n], k);
+        assert!(gain(4) > 0.5);
+        assert!(gain(11) < 0.1);
+    }
+
+    #[test]
+    fn sd_focus() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.set_sample_rate(32000.);
+        let mut p = SpectralDecayParameters::default();
+        // remove everything, but only between 6kHz and 16kHz
+        p.loss = 1.;
+        p.low_cut = 6000.;
+        p.high_cut = 16000.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| {
+            let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+            0.1 * (4. * x).sin() + 0.05 * (11. * x).sin()
+        }).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input, &mut output);
+
+        let gain = |k| dft_level(&output[7 * n..], k) / dft_level(&input[..n], k);
+        assert!((gain(4) - 1.).abs() < 0.01);
+        assert!(gain(11) < 0.01);
+    }
+
+    #[test]
+    fn sd_loss_invert() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.loss = 0.5;
+        p.loss_invert = true;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| {
+            let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+            0.5 * (4. * x).sin() + 0.1 * (11. * x).sin()
+        }).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input, &mut output);
+
+        // the loud tone is gone and the quiet one is left
+        let gain = |k| dft_level(&output[7 * n..], k) / dft_level(&input[..n], k);
+        assert!(gain(4) < 0.01);
+        assert!((gain(11) - 1.).abs() < 0.01);
+    }
+
+    #[test]
+    fn sd_loss_db() {
+        use crate::decay_processor::LOSS_DB_RANGE;
+
+        let n = 32;
+        let gains = |level: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            // lose everything under -30dBFS
+            p.loss = 1. - 30. / LOSS_DB_RANGE;
+            p.loss_db = true;
+            sd.set_params(p);
+
+            let input: Vec<_> = (0..8 * n).map(|x| {
+                let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+                level * (0.5 * (4. * x).sin() + 0.01 * (11. * x).sin())
+            }).collect();
+            let mut output = vec![0.; 8 * n];
+            sd.process(&input, &mut output);
+            let gain = |k| dft_level(&output[7 * n..], k) / dft_level(&input[..n], k);
+            (gain(4), gain(11))
+        };
+
+        // -6dBFS stays and -40dBFS goes, however loud the loudest bin is
+        let (loud, quiet) = gains(1.);
+        assert!((loud - 1.).abs() < 0.01);
+        assert!(quiet < 0.01);
+        let (loud, _) = gains(0.01);
+        assert!(loud < 0.01);
+    }
+
+    #[test]
+    fn sd_transients() {
+        let n = 32;
+        let peak = |preserve_transients: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.fuzz = 1.;
+            p.preserve_transients = preserve_transients;
+            sd.set_params(p);
+
+            let mut input = vec![0.; 8 * n];
+            input[4 * n] = 1.;
+            let mut output = vec![0.; 8 * n];
+            sd.process(&input, &mut output);
+            output.iter().fold(0f32, |max, x| x.abs().max(max))
+        };
+
+        // fuzz smears the click, unless it's let through
+        assert!(peak(0.) < 0.7);
+        assert!(peak(1.) > 0.9);
+    }
+
+    #[test]
+    fn sd_compress() {
+        let n = 32;
+        let peak_ratio = |ratio: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.compress_threshold = -40.;
+            p.compress_ratio = ratio;
+            sd.set_params(p);
+
+            // a loud and a quiet partial
+            let input: Vec<_> = (0..8 * n).map(|x| {
+                let x = x as f32 * 2. * std::f32::consts::PI / n as f32;
+                0.5 * (4. * x).sin() + 0.05 * (11. * x).sin()
+            }).collect();
+            let mut output = vec![0.; 8 * n];
+            sd.process(&input, &mut output);
+
+            // level of the loud partial relative to the quiet one
+            dft_level(&output[7 * n..], 4) / dft_level(&output[7 * n..], 11)
+        };
+
+        // compressing brings the partials closer together
+        assert!(peak_ratio(1.) > 8.);
+        assert!(peak_ratio(4.) < 4.);
+    }
+
+    #[test]
+    fn sd_dry() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n, 2 * n]);
+        let mut p = SpectralDecayParameters::default();
+        p.mix = 0.;
+        p.loss = 1.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..4 * n).map(|x| x as f32).collect();
+        let mut output = vec![0.; 4 * n];
+        sd.process(&input[..5], &mut output[..5]);
+        sd.process(&input[5..], &mut output[5..]);
+
+        let delay = sd.delay();
+        assert!(output[..delay].iter().all(|&x| x == 0.));
+        assert_eq!(output[delay..], input[..4 * n - delay]);
+    }
+
+    #[test]
+    fn sd_bypass() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.set_sample_rate(1000.);
+        let mut p = SpectralDecayParameters::default();
+        p.fuzz = 1.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..8 * n).map(|x| (x as f32 * 0.1).sin()).collect();
+        let mut output = vec![0.; 8 * n];
+        sd.process(&input[..4 * n], &mut output[..4 * n]);
+
+        // 20 samples later the dry signal is through, still delayed like the wet one
+        p.bypass = true;
+        sd.set_params(p);
+        sd.process(&input[4 * n..], &mut output[4 * n..]);
+        let delay = sd.delay();
+        for i in 4 * n + 20..8 * n {
+            assert!((output[i] - input[i - delay]).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn sd_overlap() {
+        for &overlap in &[Overlap::Half, Overlap::ThreeQuarters, Overlap::SevenEighths] {
+            let mut sd = SpectralDecay::new(&[32, 64]);
+            let mut p = SpectralDecayParameters::default();
+            p.overlap = overlap;
+            sd.set_params(p);
+
+            assert_eq!(sd.delay(), 32 + 32 / overlap.factor());
+
+            let input = vec![1.; 256];
+            let mut output = vec![0.; 256];
+            sd.process(&input, &mut output);
+
+            for x in &output[128..] {
+                assert!((*x - 1.).abs() < 1e-5, "{:?} {}", overlap, x);
+            }
+        }
+    }
+
+    #[test]
+    fn sd_grain_change() {
+        let mut sd = SpectralDecay::new(&[32, 64, 128]);
+        let mut p = SpectralDecayParameters::default();
+        // keep the delay fixed so only the grain size changes
+        p.delay_select = 1.;
+        sd.set_params(p);
+
+        let input = vec![1.; 64];
+        let mut output = Vec::new();
+        for &grain_select in &[0., 0., 0., 0.5, 0.5, 0., 0., 1., 1., 1., 0., 0., 0.] {
+            p.grain_select = grain_select;
+            sd.set_params(p);
+            let mut block = vec![0.; 64];
+            sd.process(&input, &mut block);
+            output.extend(block);
+        }
+
+        // the overlap-add stays at unity gain through every change
+        for x in &output[256..] {
+            assert!((*x - 1.).abs() < 1e-3, "{}", x);
+        }
+    }
+
+    #[test]
+    fn sd_smoothing() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        // applied right away before there's any audio
+        p.loss = 0.5;
+        sd.set_params(p);
+        let mut output = vec![0.; 8];
+        sd.process(&[0.; 8], &mut output);
+        assert_eq!(sd.frame_params.loss, 0.5);
+
+        p.loss = 1.;
+        p.freeze = true;
+        sd.set_params(p);
+        sd.process(&[0.; 8], &mut output);
+        assert!(sd.frame_params.loss > 0.5 && sd.frame_params.loss < 0.51);
+        assert!(sd.frame_params.freeze);
+
+        // reaches the target after 20ms
+        let mut output = vec![0.; 900];
+        sd.process(&[0.; 900], &mut output);
+        assert_eq!(sd.frame_params.loss, 1.);
+    }
+
+    #[test]
+    fn sd_randomize() {
+        let mut rng = random_fast_rng::FastRng::new();
+        let constraints = RandomizeConstraints {
+            fuzz: (0.2, 0.3),
+            loss: (0.5, 0.5),
+            glitch_gain: (10., 20.),
+            ..RandomizeConstraints::default()
+        };
+        let mut p = SpectralDecayParameters::default();
+        p.pitch = 7.;
+
+        for _ in 0..100 {
+            p.randomize(&mut rng, &constraints);
+            assert!(p.fuzz >= 0.2 && p.fuzz <= 0.3);
+            assert_eq!(p.loss, 0.5);
+            assert!(p.glitch_freq >= 0. && p.glitch_freq <= 1.);
+            assert!(p.glitch_gain >= 10. && p.glitch_gain <= 20.00001);
+            assert_eq!(p.pitch, 7.);
+        }
+    }
+
+    #[test]
+    fn sd_synthesis() {
+        let n = 32;
+        let run = |synthesis_select: f32, fuzz: f32| {
+            let mut sd = SpectralDecay::new(&[n, 2 * n, 4 * n]);
+            let mut p = SpectralDecayParameters::default();
+            p.grain_select = 1.;
+            p.synthesis_select = synthesis_select;
+            p.fuzz = fuzz;
+            sd.set_params(p);
+            let delay = sd.delay();
+
+            let mut input = vec![0.; 16 * n];
+            input[4 * n] = 1.;
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            (output, 4 * n + delay)
+        };
+
+        // still an identity, up to the ripple the weights leave alone, despite the windows being different
+        let (output, at) = run(0., 0.);
+        for (i, y) in output.iter().enumerate() {
+            assert!((y - if i == at { 1. } else { 0. }).abs() < 2e-3, "{} {}", i, y);
+        }
+
+        // fuzz smears each grain over its synthesis window, which can't be shorter than 2 hops of the 4n grain,
+        // so nothing comes out further than half a grain and half a synthesis window from the impulse
+        let outside = |(output, at): (Vec<f32>, usize)| output.iter().enumerate()
+            .filter(|&(i, _)| (i as isize - at as isize).abs() >= (2 * n + n) as isize)
+            .map(|(_, y)| y * y)
+            .sum::<f32>();
+        assert_eq!(outside(run(0., 1.)), 0.);
+        assert!(outside(run(1., 1.)) > 1e-4);
+    }
+
+    #[test]
+    fn sd_stretch() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        // a tone for the first half, silence for the second
+        let input: Vec<_> = (0..64 * n).map(|i| if i < 32 * n { (2. * PI * 8. * i as f32 / n as f32).cos() } else { 0. }).collect();
+        let energy = |x: &[f32]| x.iter().map(|y| y * y).sum::<f32>() / x.len() as f32;
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let output = sd.render_stretched(&input, 2.);
+        assert_eq!(output.len(), 128 * n);
+        // the tone lasts twice as long, at the same pitch
+        let tone = &output[8 * n..56 * n];
+        assert!((energy(tone) - 0.5).abs() < 0.05, "{}", energy(tone));
+        assert!(dft_level(tone, 8 * 48) / tone.len() as f32 > 0.45);
+        assert!(energy(&output[72 * n..120 * n]) < 1e-6);
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let output = sd.render_stretched(&input, 0.5);
+        assert_eq!(output.len(), 32 * n);
+        let tone = &output[2 * n..14 * n];
+        assert!((energy(tone) - 0.5).abs() < 0.05, "{}", energy(tone));
+        assert!(dft_level(tone, 8 * 12) / tone.len() as f32 > 0.45);
+        assert!(energy(&output[18 * n..30 * n]) < 1e-6);
+
+        // live, the analysis can't fall further behind than the input history goes back
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.set_max_lag(4 * n);
+        let mut p = SpectralDecayParameters::default();
+        p.stretch = 4.;
+        sd.set_params(p);
+        let mut output = vec![0.; input.len()];
+        sd.process(&input, &mut output);
+        assert!(sd.lag <= (4 * n) as f32);
+        assert!(output.iter().all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn sd_jitter() {
+        let n = 32;
+        let run = |jitter: f32, seed: u64| {
+            let mut sd = SpectralDecay::new(&[n]);
+            sd.set_seed(Some(seed));
+            let mut p = SpectralDecayParameters::default();
+            p.jitter = jitter;
+            sd.set_params(p);
+            let delay = sd.delay();
+
+            let mut input = vec![0.; 32 * n];
+            for i in (4 * n..input.len()).step_by(n) {
+                input[i] = 1.;
+            }
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            (output, 4 * n + delay)
+        };
+
+        let (still, _) = run(0., 1);
+        let (jittered, at) = run(1., 1);
+        assert_ne!(still, jittered);
+        assert_eq!(jittered, run(1., 1).0);
+        assert_ne!(jittered, run(1., 2).0);
+        // grains only ever come from further back, so nothing comes out early
+        assert!(jittered[..at - n].iter().all(|&x| x == 0.));
+    }
+
+    #[test]
+    fn sd_jitter_coherent() {
+        use std::f32::consts::PI;
+
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.jitter = 1.;
+        sd.set_params(p);
+
+        // grains from all over a steady tone still add up to the whole tone
+        let input: Vec<_> = (0..64 * n).map(|i| (2. * PI * 4. * i as f32 / n as f32).cos()).collect();
+        let mut output = vec![0.; input.len()];
+        sd.process(&input, &mut output);
+        let tail = &output[48 * n..];
+        assert!(dft_level(tail, 4 * 16) > 0.95 * tail.len() as f32 / 2.);
+    }
+
+    #[test]
+    fn sd_reverse() {
+        let n = 32;
+        let run = |reverse: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            sd.set_seed(Some(1));
+            let mut p = SpectralDecayParameters::default();
+            p.reverse = reverse;
+            sd.set_params(p);
+            let delay = sd.delay();
+
+            let mut input = vec![0.; 16 * n];
+            input[4 * n] = 1.;
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            (output, 4 * n + delay)
+        };
+        let before = |(output, at): &(Vec<f32>, usize)| output[at - n..*at].iter().map(|y| y * y).sum::<f32>();
+
+        // backwards grains put the impulse before where it was as well as after
+        assert!(before(&run(0.)) < 1e-9);
+        assert!(before(&run(1.)) > 1e-2);
+    }
+
+    #[test]
+    fn sd_zero_padded() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let table = Arc::new(GrainTable::new(&[n], WindowKind::Hann).unwrap());
+        let mut sd = SpectralDecay::with_plans(GrainPlans::zero_padded(table).unwrap());
+        let mut tap = sd.spectrum_tap();
+        let delay = sd.delay();
+
+        let input: Vec<_> = (0..n * 8).map(|i| (2. * PI * 8. * i as f32 / n as f32).cos()).collect();
+        let mut output = vec![0.; input.len()];
+        sd.process(&input, &mut output);
+
+        // the same tone, at twice the resolution
+        let (bins, fft_size) = tap.read();
+        assert_eq!(fft_size, 2 * n);
+        assert_eq!(bins.len(), n + 1);
+        assert!((bins[16] - 1.).abs() < 0.01);
+        assert!(bins[40] < 0.01);
+
+        // and the padding is cropped off again
+        for i in delay + n..input.len() {
+            assert!((output[i] - input[i - delay]).abs() < 1e-4);
+        }
+
+        assert!(sd.set_zero_padded(false).is_ok());
+        let mut sd = SpectralDecay::new(&[n]);
+        assert_eq!(
+            sd.set_zero_padded(true),
+            Err(SpectralDecayError::GrainSizeTooLarge { size: 2 * n, capacity: n })
+        );
+    }
+
+    #[test]
+    fn sd_spectrum_tap() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+
+        let input: Vec<_> = (0..n * 4).map(|i| (2. * PI * 8. * i as f32 / n as f32).cos()).collect();
+        let mut output = vec![0.; input.len()];
+        sd.process(&input, &mut output);
+
+        let (bins, grain_size) = tap.read();
+        assert_eq!(grain_size, n);
+        assert_eq!(bins.len(), n / 2 + 1);
+        assert!((bins[8] - 1.).abs() < 0.01);
+        assert!(bins[20] < 0.01);
+    }
+
+    #[test]
+    fn sd_meters() {
+        let n = 64;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.mix = 0.;
+        sd.set_params(p);
+        let meters = sd.meters();
+
+        let mut output = vec![0.; n];
+        sd.process(&vec![0.5; n], &mut output);
+        assert_eq!(meters.input().peak, 0.5);
+        // the dry signal hasn't made it through the delay yet
+        assert_eq!(meters.output().peak, 0.);
+    }
+
+    #[test]
+    fn sd_lfo() {
+        use crate::lfo::{LfoShape, LfoTarget};
+
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        p.fuzz = 0.5;
+        // a square wave at a quarter of the frame rate, so two frames up then two down
+        p.lfo[1] = LfoParameters {
+            rate: 44100. / 8. / 4.,
+            depth: 0.25,
+            shape: LfoShape::Square,
+            target: LfoTarget::Fuzz,
+        };
+        sd.set_params(p);
+
+        let mut fuzz = vec![];
+        let mut output = vec![0.; 8];
+        for _ in 0..4 {
+            sd.process(&[0.; 8], &mut output);
+            fuzz.push(sd.frame_params.fuzz);
+        }
+        assert_eq!(fuzz, vec![0.75, 0.75, 0.25, 0.25]);
+        // the unmodulated value is what ramps start from
+        assert_eq!(sd.ramped.fuzz, 0.5);
+    }
+
+    #[test]
+    fn sd_processor() {
+        struct Mute(usize);
+        impl SpectralProcessor for Mute {
+            fn process(&mut self, bins: &mut [Complex<f32>], ctx: FrameCtx) {
+                assert_eq!(bins.len(), ctx.grain_size / 2 + 1);
+                self.0 += 1;
+                for x in bins.iter_mut() {
+                    *x = Complex::zero();
+                }
+            }
+        }
+
+        let mut sd = SpectralDecay::with_processor(&[32], WindowKind::Hann, Mute(0));
+        let input = vec![1.; 64];
+        let mut output = vec![1.; 64];
+        sd.process(&input, &mut output);
+
+        assert_eq!(sd.processor().0, 64 / 8);
+        assert!(output.iter().all(|&x| x == 0.));
+    }
+
+    #[test]
+    fn sd_sidechain_duck() {
+        let mut sd = SpectralDecay::new(&[32]);
+        let mut p = SpectralDecayParameters::default();
+        p.duck = 1.;
+        sd.set_params(p);
+
+        let input: Vec<_> = (0..128).map(|x| (x as f32 * 0.5).sin()).collect();
+        let mut output = vec![0.; 128];
+
+        // ducking by itself cancels everything
+        sd.process_sidechain(&input, &input, &mut output);
+        assert!(output.iter().all(|x| x.abs() < 1e-6));
+
+        // without a sidechain it passes through
+        sd.process(&input, &mut output);
+        assert!(output[64..].iter().any(|x| x.abs() > 0.1));
+    }
+
+    #[test]
+    fn sd_glitch_decay() {
+        let n = 32;
+        let tone: Vec<_> = (0..4 * n).map(|i| (2. * std::f32::consts::PI * 4. * i as f32 / n as f32).sin()).collect();
+        let after = |glitch_decay: f32| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.glitch_freq = 1.;
+            p.glitch_gain = 100.;
+            p.glitch_synced = true;
+            p.glitch_decay = glitch_decay;
+            sd.set_params(p);
+            let mut output = vec![0.; 4 * n];
+            sd.process(&tone, &mut output);
+            sd.trigger_glitch();
+            sd.process(&tone[..n], &mut output[..n]);
+            sd.process(&tone, &mut output);
+            output[2 * n..].iter().map(|x| x * x).sum::<f32>()
+        };
+
+        // well after the burst, it's still fading out instead of long gone
+        assert!(after(64.) > 2. * after(0.));
+    }
+
+    #[test]
+    fn sd_sidechain_morph() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let tone = |k: f32| (0..n * 4).map(|i| (2. * PI * k * i as f32 / n as f32).cos()).collect::<Vec<_>>();
+        let (input, sidechain) = (tone(8.), tone(20.));
+        let mut output = vec![0.; input.len()];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.morph = 0.5;
+        sd.set_params(p);
+        sd.process_sidechain(&input, &sidechain, &mut output);
+
+        // half of each, at frequencies the input has some energy in
+        let (bins, _) = tap.read();
+        assert!((bins[8] - 0.5).abs() < 0.01);
+        assert!((bins[20] - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn sd_sidechain_vocode() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let tone = |k: f32| (0..n * 4).map(|i| (2. * PI * k * i as f32 / n as f32).cos()).collect::<Vec<_>>();
+        let input: Vec<_> = tone(8.).iter().zip(tone(20.)).map(|(a, b)| a + b).collect();
+        let sidechain = tone(20.);
+        let mut output = vec![0.; input.len()];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.vocode = 1.;
+        sd.set_params(p);
+        sd.process_sidechain(&input, &sidechain, &mut output);
+
+        // only what the sidechain has energy around gets through
+        let (bins, _) = tap.read();
+        assert!(bins[8] < 0.01);
+        assert!((bins[20] - 1.).abs() < 0.01);
+    }
+
+    #[test]
+    fn sd_print() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let tone = |k: f32| (0..n * 4).map(|i| (2. * PI * k * i as f32 / n as f32).cos()).collect::<Vec<_>>();
+        let both: Vec<_> = tone(8.).iter().zip(tone(20.)).map(|(a, b)| a + b).collect();
+        let mut output = vec![0.; both.len()];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.print = 1.;
+        sd.set_params(p);
+
+        // nothing happens without a print
+        sd.start_print_capture();
+        sd.process(&tone(20.), &mut output);
+        let (bins, _) = tap.read();
+        assert!((bins[8]).abs() < 0.01);
+        assert!((bins[20] - 1.).abs() < 0.01);
+
+        // only the captured tone gets through after
+        sd.finish_print_capture();
+        assert_eq!(sd.print().unwrap().magnitudes().len(), n / 2 + 1);
+        sd.process(&both, &mut output);
+        let (bins, _) = tap.read();
+        assert!(bins[8] < 0.01);
+        assert!((bins[20] - 1.).abs() < 0.01);
+    }
+
+    #[test]
+    fn sd_denoise() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let tone = |k: f32| (0..n * 4).map(|i| (2. * PI * k * i as f32 / n as f32).cos()).collect::<Vec<_>>();
+        let both: Vec<_> = tone(8.).iter().zip(tone(20.)).map(|(a, b)| a + b).collect();
+        let mut output = vec![0.; both.len()];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.denoise = 2.;
+        p.denoise_floor = 0.;
+        sd.set_params(p);
+
+        // learn one tone as the noise
+        sd.start_noise_learning();
+        sd.process(&tone(8.), &mut output);
+        sd.finish_noise_learning();
+        assert_eq!(sd.noise_profile().unwrap().magnitudes().len(), n / 2 + 1);
+
+        // then it's taken out of the mix, leaving the other one
+        sd.process(&both, &mut output);
+        let (bins, _) = tap.read();
+        assert!(bins[8] < 0.01);
+        assert!((bins[20] - 1.).abs() < 0.01);
+    }
+
+    #[test]
+    fn sd_spread() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let input: Vec<_> = (0..n * 4).map(|i| (2. * PI * 8. * i as f32 / n as f32).cos()).collect();
+        let mut output = vec![0.; input.len()];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.spread = 2.;
+        sd.set_params(p);
+        sd.process(&input, &mut output);
+
+        // the tone moves up an octave
+        let (bins, _) = tap.read();
+        assert!(bins[8] < 0.01);
+        assert!((bins[16] - 1.).abs() < 0.01);
+    }
+
+    #[test]
+    fn sd_formant() {
+        use std::f32::consts::PI;
+
+        let n = 1024;
+        let input: Vec<_> = (0..n * 4).map(|i| (2. * PI * 64. * i as f32 / n as f32).cos()).collect();
+        let mut output = vec![0.; input.len()];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut tap = sd.spectrum_tap();
+        let mut p = SpectralDecayParameters::default();
+        p.formant = 12.;
+        sd.set_params(p);
+        sd.process(&input, &mut output);
+
+        // the tone stays put, but the envelope it was the peak of has moved an octave up
+        let (bins, _) = tap.read();
+        assert!(bins[64] < 0.5);
+        assert!(output.iter().all(|x| x.is_finite()));
+    }
+
+    #[test]
+    fn sd_lifter() {
+        use std::f32::consts::PI;
+
+        let n = 1024;
+        let input: Vec<_> = (0..n * 4).map(|i| (2. * PI * 64. * i as f32 / n as f32).cos()).collect();
+        let lifted = |lifter: f32| {
+            let mut output = vec![0.; input.len()];
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut tap = sd.spectrum_tap();
+            let mut p = SpectralDecayParameters::default();
+            p.lifter = lifter;
+            sd.set_params(p);
+            sd.process(&input, &mut output);
+            assert!(output.iter().all(|x| x.is_finite()));
+            tap.read().0.to_vec()
+        };
+
+        // envelope only flattens the tone into the smooth bump around it
+        let bins = lifted(1.);
+        assert!(bins[64] < 0.5);
+        // fine structure only keeps the tone where it is
+        let bins = lifted(-1.);
+        assert!(bins[64] > 0.5);
+    }
+
+    #[test]
+    fn sd_seed() {
+        let n = 32;
+        let input: Vec<_> = (0..8 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+        let run = |seed: Option<u64>| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.fuzz = 1.;
+            p.glitch_freq = 1.;
+            p.glitch_gain = 10.;
+            sd.set_params(p);
+            sd.set_seed(seed);
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            output
+        };
+
+        assert_eq!(run(Some(1)), run(Some(1)));
+        assert_ne!(run(Some(1)), run(Some(2)));
+    }
+
+    #[test]
+    fn sd_seed_silence() {
+        let n = 32;
+        let mut p = SpectralDecayParameters::default();
+        p.fuzz = 1.;
+        p.glitch_freq = 1.;
+        p.glitch_gain = 10.;
+        let mut sds = [SpectralDecay::new(&[n]), SpectralDecay::new(&[n])];
+        for sd in sds.iter_mut() {
+            sd.set_params(p);
+            sd.set_seed(Some(1));
+        }
+
+        // one side goes quiet and skips frames while the other is still playing
+        let len = 4 * SILENT_FRAMES * n;
+        let mut input = vec![0.; len];
+        for (i, x) in input[..8 * n].iter_mut().enumerate() {
+            *x = 0.1 * (i as f32 * 0.3).sin();
+        }
+        let mut output = vec![0.; len];
+        sds[0].process(&vec![0.; len], &mut output);
+        sds[1].process(&input, &mut output);
+        assert!(sds.iter().all(|sd| sd.is_silent()));
+
+        let input: Vec<_> = (0..8 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+        let mut outputs = [vec![0.; input.len()], vec![0.; input.len()]];
+        for (sd, output) in sds.iter_mut().zip(outputs.iter_mut()) {
+            sd.process(&input, output);
+        }
+        assert_eq!(outputs[0], outputs[1]);
+    }
+
+    #[test]
+    fn sd_reset() {
+        let mut sd = SpectralDecay::new(&[32, 64]);
+        let mut p = SpectralDecayParameters::default();
+        p.freeze = true;
+        sd.set_params(p);
+
+        let input = vec![1.; 100];
+        let mut output = vec![0.; 100];
+        sd.process(&input, &mut output);
+
+        sd.reset();
+
+        // no stale tail, and the freeze latches the new (silent) input
+        let input = vec![0.; 100];
+        sd.process(&input, &mut output);
+        assert!(output.iter().all(|&x| x == 0.));
+    }
+
+    #[test]
+    fn sd_reset_in_progress() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let tone = |k: f32| (0..n * 4).map(|i| (2. * PI * k * i as f32 / n as f32).cos()).collect::<Vec<_>>();
+        let mut output = vec![0.; 4 * n];
+
+        let mut sd = SpectralDecay::new(&[n]);
+        let mut p = SpectralDecayParameters::default();
+        sd.set_params(p);
+        sd.process(&tone(8.), &mut output);
+
+        // halfway through fading to bypass
+        p.bypass = true;
+        sd.set_params(p);
+        sd.process(&tone(8.)[..n], &mut output[..n]);
+        assert!(sd.bypass_fade > 0. && sd.bypass_fade < 1.);
+
+        // what went in before the reset isn't part of the capture
+        sd.start_print_capture();
+        sd.start_noise_learning();
+        sd.process(&tone(8.), &mut output);
+        sd.reset();
+        assert_eq!(sd.bypass_fade, 1.);
+        sd.process(&tone(20.), &mut output);
+        sd.process(&tone(20.), &mut output);
+        sd.finish_print_capture();
+        sd.finish_noise_learning();
+        for magnitudes in &[sd.print().unwrap().magnitudes(), sd.noise_profile().unwrap().magnitudes()] {
+            assert!(magnitudes[8] < 0.1 * magnitudes[20]);
+        }
+    }
+
+    #[test]
+    fn sd_latency_changed() {
+        let mut sd = SpectralDecay::new(&[32, 64]);
+        assert_eq!(sd.latency_changed(), None);
+
+        let mut p = SpectralDecayParameters::default();
+        p.grain_select = 1.;
+        sd.set_params(p);
+        assert_eq!(sd.latency_changed(), Some(sd.delay()));
+        assert_eq!(sd.latency_changed(), None);
+
+        // only reported when it's actually different
+        p.mix = 0.5;
+        sd.set_params(p);
+        assert_eq!(sd.latency_changed(), None);
+        p.grain_select = 0.;
+        sd.set_params(p);
+        assert_eq!(sd.latency_changed(), Some(sd.delay()));
+    }
+
+    #[test]
+    fn sd_swap_grains() {
+        let n = 32;
+        let mut sd = SpectralDecay::new(&[n, 2 * n, 4 * n]);
+        let mut p = SpectralDecayParameters::default();
+        p.grain_select = 1.;
+        sd.set_params(p);
+        assert_eq!(sd.delay(), 4 * n + n);
+
+        let input = vec![1.; 16 * n];
+        let mut output = vec![0.; 16 * n];
+        sd.process(&input[..8 * n], &mut output[..8 * n]);
+
+        // the longest grain is now the middle one
+        sd.set_grain_sizes(&[n, 2 * n]).unwrap();
+        assert_eq!(sd.delay(), 2 * n + n / 2);
+        sd.process(&input[8 * n..], &mut output[8 * n..]);
+        for x in &output[14 * n..] {
+            assert!((*x - 1.).abs() < 1e-3, "{}", x);
+        }
+
+        assert_eq!(
+            sd.set_grain_sizes(&[8 * n]),
+            Err(SpectralDecayError::GrainSizeTooLarge { size: 8 * n, capacity: 4 * n })
+        );
+    }
+
+    #[test]
+    fn sd_errors() {
+        assert_eq!(SpectralDecay::try_new(&[]).err(), Some(SpectralDecayError::NoGrainSizes));
+        assert_eq!(SpectralDecay::try_new(&[32, 34]).err(), Some(SpectralDecayError::InvalidGrainSize(34)));
+        assert_eq!(SpectralDecay::try_new(&[0, 32]).err(), Some(SpectralDecayError::InvalidGrainSize(0)));
+        assert_eq!(SpectralDecay::try_new(&[64, 32]).err(), Some(SpectralDecayError::UnsortedGrainSizes));
+        assert!(SpectralDecay::try_new(&[32, 32, 64]).is_ok());
+    }
+
+    #[test]
+    fn sd_render() {
+        let n = 32;
+        let input: Vec<_> = (0..10 * n).map(|x| (x as f32 * 0.3).sin()).collect();
+
+        // the same as processing by hand, minus the latency
+        let mut sd = SpectralDecay::new(&[n]);
+        let delay = sd.delay();
+        let mut padded = input.clone();
+        padded.resize(input.len() + delay, 0.);
+        let mut expected = vec![0.; padded.len()];
+        sd.process(&padded, &mut expected);
+
+        let mut progress = Vec::new();
+        let output = SpectralDecay::new(&[n]).render(&input, &[], |x| progress.push(x));
+        assert_eq!(output, &expected[delay..]);
+        assert_eq!(progress.last(), Some(&1.));
+
+        // automation takes effect at its sample, as if set between process calls
+        let mut lossy = SpectralDecayParameters::default();
+        lossy.loss = 0.5;
+        let mut sd = SpectralDecay::new(&[n]);
+        sd.process(&padded[..5 * n], &mut expected[..5 * n]);
+        sd.set_params(lossy);
+        sd.process(&padded[5 * n..], &mut expected[5 * n..]);
+
+        let output = SpectralDecay::new(&[n]).render(&input, &[(0, SpectralDecayParameters::default()), (5 * n, lossy)], |_| ());
+        assert_eq!(output, &expected[delay..]);
+    }
+
+    #[test]
+    fn sd_non_finite() {
+        let mut sd = SpectralDecay::new(&[32]);
+
+        let mut input = vec![1.; 64];
+        input[10] = f32::NAN;
+        input[20] = f32::INFINITY;
+        let mut output = vec![0.; 64];
+        sd.process(&input, &mut output);
+        assert!(output.iter().all(|x| x.is_finite()));
+
+        // and it recovers
+        let input = vec![1.; 256];
+        let mut output = vec![0.; 256];
+        sd.process(&input, &mut output);
+        assert!(output.iter().all(|x| x.is_finite()));
+        assert!((output[255] - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sd_dc_block() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let input: Vec<_> = (0..n * 4).map(|i| 0.5 + (2. * PI * 8. * i as f32 / n as f32).cos()).collect();
+        let mut output = vec![0.; input.len()];
+
+        for &dc_block in &[false, true] {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut tap = sd.spectrum_tap();
+            let mut p = SpectralDecayParameters::default();
+            p.dc_block = dc_block;
+            sd.set_params(p);
+            sd.process(&input, &mut output);
+
+            let (bins, _) = tap.read();
+            assert_eq!(bins[0] == 0., dc_block);
+            assert!((bins[8] - 1.).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn sd_auto_gain() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let input: Vec<_> = (0..n * 8).map(|i| 4. * (2. * PI * 8. * i as f32 / n as f32).cos()).collect();
+        let run = |auto_gain: bool| {
+            let mut sd = SpectralDecay::new(&[n]);
+            let mut p = SpectralDecayParameters::default();
+            p.auto_gain = auto_gain;
+            sd.set_params(p);
+            let delay = sd.delay();
+            let mut output = vec![0.; input.len()];
+            sd.process(&input, &mut output);
+            (output, delay)
+        };
+
+        // turned down well under the input's peak
+        let (output, _) = run(true);
+        assert!(output[4 * n..].iter().all(|x| x.abs() < 3.));
+
+        // or left exactly as loud as it went in
+        let (output, delay) = run(false);
+        for (y, x) in output[4 * n..].iter().zip(&input[4 * n - delay..]) {
+            assert!((y - x).abs() < 1e-3);
+        }
+    }
+
+    mod properties {
+        use super::*;
+        use proptest::prelude::*;
+
+        fn overlaps() -> impl Strategy<Value = Overlap> {
+            prop_oneof![Just(Overlap::Half), Just(Overlap::ThreeQuarters), Just(Overlap::SevenEighths)]
+        }
+
+        proptest! {
+            // big grains make every case slow
+            #![proptest_config(ProptestConfig::with_cases(32))]
+
+            // neutral parameters only delay the input, whatever the grain size and however it's split into blocks
+            #[test]
+            fn sd_identity_any_grain(
+                size in prop::sample::select(fft_sizes::default_sizes(44100.)),
+                overlap in overlaps(),
+                blocks in prop::collection::vec(1..2048usize, 1..16),
+                seed in any::<u32>(),
+            ) {
+                let mut sd = SpectralDecay::new(&[size]);
+                let mut p = SpectralDecayParameters::default();
+                p.overlap = overlap;
+                sd.set_params(p);
+                let delay = sd.delay();
+
+                // noise from a xorshift, skipping the first grain after the latency while the overlap-add fills up
+                let len = delay + 2 * size;
+                let mut state = seed | 1;
+                let input: Vec<f32> = (0..len).map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as f32 / u32::MAX as f32 - 0.5
+                }).collect();
+                let mut output = vec![0.; len];
+
+                let mut start = 0;
+                for &block in blocks.iter().cycle() {
+                    if start == len {
+                        break;
+                    }
+                    let end = (start + block).min(len);
+                    sd.process(&input[start..end], &mut output[start..end]);
+                    start = end;
+                }
+
+                for (i, (y, x)) in output[delay + size..].iter().zip(&input[size..]).enumerate() {
+                    prop_assert!((y - x).abs() < 1e-3, "sample {}: {} != {}", size + i, y, x);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,67 @@
+use crate::ring_buffer::RingBuffer;
+use crate::denormal::flush_complex;
+use rustfft::num_complex::Complex;
+
+pub const MAX_DELAY_FRAMES: usize = 64;
+
+// delays each bin by a number of frames proportional to its frequency
+pub struct SpectralDelay {
+    lines: Vec<RingBuffer<Complex<f32>>>,
+}
+
+impl SpectralDelay {
+    pub fn new(max_bins: usize) -> Self {
+        Self {
+            lines: (0..max_bins).map(|_| RingBuffer::new(MAX_DELAY_FRAMES, true)).collect()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for line in self.lines.iter_mut() {
+            line.clear_fill();
+        }
+    }
+
+    // frames is the delay of the highest bin, feedback is how much of the delayed bin is fed back in
+    pub fn process(&mut self, bins: &mut [Complex<f32>], frames: f32, feedback: f32) {
+        let frames = frames.max(0.).min(MAX_DELAY_FRAMES as f32);
+        let top = (bins.len() - 1).max(1) as f32;
+
+        for (k, (x, line)) in bins.iter_mut().zip(self.lines.iter_mut()).enumerate() {
+            let delay = (frames * k as f32 / top).round() as usize;
+            if delay == 0 {
+                continue;
+            }
+
+            let delayed = *line.iter(-(delay as isize)).next().unwrap();
+            line.push_overwrite(flush_complex(*x + delayed * feedback));
+            *x = delayed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustfft::num_traits::Zero;
+
+    #[test]
+    fn spectral_delay() {
+        let mut sd = SpectralDelay::new(3);
+        let mut bins = [Complex::new(1., 0.); 3];
+
+        sd.process(&mut bins, 4., 0.);
+        // the lowest bin isn't delayed, the middle is delayed by 2 frames, the highest by 4
+        assert_eq!(bins, [Complex::new(1., 0.), Complex::zero(), Complex::zero()]);
+
+        let mut history = Vec::new();
+        for _ in 0..4 {
+            let mut bins = [Complex::zero(); 3];
+            sd.process(&mut bins, 4., 0.);
+            history.push(bins);
+        }
+        assert_eq!(history[1][1], Complex::new(1., 0.));
+        assert_eq!(history[3][2], Complex::new(1., 0.));
+        assert_eq!(history[0][1] + history[2][1] + history[0][2] + history[2][2], Complex::zero());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::spectral_decay::SpectralDecayError;
+use crate::window::WindowKind;
+use crate::fft_plans;
+use rustfft::num_complex::Complex;
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// the average magnitude spectrum of some sound, relative to its loudest bin,
+// for filtering other sounds into its shape
+// stored at the resolution it was captured at, and stretched to whatever grain size it's applied to
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpectralPrint {
+    magnitudes: Vec<f32>,
+}
+
+impl SpectralPrint {
+    // averaged over every grain_size / 4 hop of samples, padded with silence at the end
+    pub fn from_audio(samples: &[f32], grain_size: usize) -> Result<Self, SpectralDecayError> {
+        if grain_size == 0 || grain_size % 2 != 0 {
+            return Err(SpectralDecayError::InvalidGrainSize(grain_size));
+        }
+        let window = WindowKind::Hann.generate(grain_size);
+        let (fft, _) = fft_plans::plans(grain_size);
+        let mut scratch = fft.make_scratch_vec();
+        let mut time_buf = vec![0.; grain_size];
+        let mut freq_buf = vec![Complex::new(0., 0.); grain_size / 2 + 1];
+
+        let mut sum = vec![0.; freq_buf.len()];
+        for start in (0..samples.len()).step_by((grain_size / 4).max(1)) {
+            for (i, (y, &w)) in time_buf.iter_mut().zip(&window).enumerate() {
+                *y = samples.get(start + i).copied().unwrap_or(0.) * w;
+            }
+            fft.process_with_scratch(&mut time_buf, &mut freq_buf, &mut scratch).unwrap();
+            for (y, x) in sum.iter_mut().zip(&freq_buf) {
+                *y += x.norm();
+            }
+        }
+
+        let mut print = Self::default();
+        print.set_sum(&sum);
+        Ok(print)
+    }
+
+    // from summed magnitudes, reusing the allocation if it's big enough
+    pub(crate) fn set_sum(&mut self, sum: &[f32]) {
+        let peak = sum.iter().fold(0., |max: f32, &x| x.max(max));
+        self.magnitudes.clear();
+        self.magnitudes.extend(sum.iter().map(|&x| if peak > 0. { x / peak } else { 0. }));
+    }
+
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.magnitudes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.magnitudes.is_empty()
+    }
+
+    // linearly interpolated gain of bin k out of num_bins, 0..1
+    pub fn gain(&self, k: usize, num_bins: usize) -> f32 {
+        match self.magnitudes.len() {
+            0 => 1.,
+            1 => self.magnitudes[0],
+            len => {
+                let x = k as f32 * (len - 1) as f32 / (num_bins - 1).max(1) as f32;
+                let i = (x as usize).min(len - 2);
+                let t = (x - i as f32).min(1.);
+                self.magnitudes[i] + (self.magnitudes[i + 1] - self.magnitudes[i]) * t
+            }
+        }
+    }
+}
+
+pub fn save(path: impl AsRef<Path>, print: &SpectralPrint) -> io::Result<()> {
+    let json = serde_json::to_string(print)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, json)
+}
+
+pub fn load(path: impl AsRef<Path>) -> io::Result<SpectralPrint> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print_from_audio() {
+        use std::f32::consts::PI;
+
+        let n = 64;
+        let input: Vec<_> = (0..n * 8).map(|i| (2. * PI * 8. * i as f32 / n as f32).cos()).collect();
+        let print = SpectralPrint::from_audio(&input, n).unwrap();
+
+        assert_eq!(print.magnitudes().len(), n / 2 + 1);
+        assert_eq!(print.gain(8, n / 2 + 1), 1.);
+        assert!(print.gain(20, n / 2 + 1) < 0.01);
+        // the same frequency at twice the resolution
+        assert_eq!(print.gain(16, n + 1), 1.);
+        assert!((print.gain(17, n + 1) - (print.gain(8, n / 2 + 1) + print.gain(9, n / 2 + 1)) / 2.).abs() < 1e-6);
+    }
+}
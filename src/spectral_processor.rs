@@ -0,0 +1,36 @@
+use crate::spectral_decay::SpectralDecayParameters;
+use rustfft::num_complex::Complex;
+
+// everything a processor might want to know about the frame it's given
+#[derive(Copy, Clone)]
+pub struct FrameCtx<'a> {
+    pub params: &'a SpectralDecayParameters,
+    // index into the grain sizes SpectralDecay was constructed with
+    pub grain_index: usize,
+    pub grain_size: usize,
+    // grain_size, or twice that when zero padded
+    pub fft_size: usize,
+    pub hop: usize,
+    // bin k is at k * sample_rate / fft_size Hz
+    pub sample_rate: f32,
+    // magnitude of a full scale sine's bin, for comparing bins against absolute levels
+    pub full_scale: f32,
+    // spectrum of the sidechain for the same frame, analyzed the same way as bins
+    pub sidechain: Option<&'a [Complex<f32>]>,
+    // SpectralDecay::trigger_glitch() was called since the last frame
+    pub glitch_trigger: bool,
+    // SpectralDecay::clear_accumulation() was called since the last frame
+    pub accumulate_clear: bool,
+}
+
+// the per-frame spectrum stage of SpectralDecay, which handles the STFT and overlap-add around it
+// bins has fft_size / 2 + 1 entries, with the window and normalization already applied
+pub trait SpectralProcessor {
+    fn process(&mut self, bins: &mut [Complex<f32>], ctx: FrameCtx);
+
+    // forget any state carried between frames, see SpectralDecay::reset
+    fn reset(&mut self) {}
+
+    // restart whatever random sequence the processor draws from, see SpectralDecay::set_seed
+    fn reseed(&mut self, _seed: u64) {}
+}
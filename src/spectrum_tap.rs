@@ -0,0 +1,91 @@
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// triple buffered magnitude spectra, for getting the latest frame off the process thread
+// the writer always has a buffer to fill and the reader always has one to look at,
+// the third is handed back and forth, neither side allocates, locks or waits
+
+// set on the shared index when the writer has handed over a buffer the reader hasn't seen
+const FRESH: usize = 4;
+
+struct Spectrum {
+    bins: Vec<f32>,
+    grain_size: usize,
+}
+
+struct Shared {
+    buffers: [UnsafeCell<Spectrum>; 3],
+    // the buffer that's in between, plus FRESH
+    middle: AtomicUsize,
+}
+
+// each buffer is only accessed by the side whose index points to it
+unsafe impl Sync for Shared {}
+unsafe impl Send for Shared {}
+
+pub(crate) struct SpectrumWriter {
+    shared: Arc<Shared>,
+    write: usize,
+}
+
+pub struct SpectrumTap {
+    shared: Arc<Shared>,
+    read: usize,
+}
+
+pub(crate) fn spectrum_tap(max_bins: usize) -> (SpectrumWriter, SpectrumTap) {
+    let buffer = || UnsafeCell::new(Spectrum { bins: Vec::with_capacity(max_bins), grain_size: 0 });
+    let shared = Arc::new(Shared {
+        buffers: [buffer(), buffer(), buffer()],
+        middle: AtomicUsize::new(1),
+    });
+    (SpectrumWriter { shared: shared.clone(), write: 0 }, SpectrumTap { shared, read: 2 })
+}
+
+impl SpectrumWriter {
+    // bins longer than max_bins are cut off
+    pub fn publish(&mut self, grain_size: usize, bins: impl Iterator<Item = f32>) {
+        let spectrum = unsafe { &mut *self.shared.buffers[self.write].get() };
+        let max_bins = spectrum.bins.capacity();
+        spectrum.bins.clear();
+        spectrum.bins.extend(bins.take(max_bins));
+        spectrum.grain_size = grain_size;
+
+        self.write = self.shared.middle.swap(self.write | FRESH, Ordering::AcqRel) & !FRESH;
+    }
+}
+
+impl SpectrumTap {
+    // magnitudes of bins 0 to fft_size / 2 of the latest frame, and the FFT size for mapping them to frequencies,
+    // which is the grain size unless it's zero padded
+    // the same frame again if there hasn't been a new one, empty before the first one
+    pub fn read(&mut self) -> (&[f32], usize) {
+        if self.shared.middle.load(Ordering::Relaxed) & FRESH != 0 {
+            self.read = self.shared.middle.swap(self.read, Ordering::AcqRel) & !FRESH;
+        }
+        let spectrum = unsafe { &*self.shared.buffers[self.read].get() };
+        (&spectrum.bins, spectrum.grain_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tap_read() {
+        let (mut writer, mut tap) = spectrum_tap(4);
+        assert_eq!(tap.read(), (&[][..], 0));
+
+        writer.publish(4, [1., 2., 3.].iter().copied());
+        assert_eq!(tap.read(), (&[1., 2., 3.][..], 4));
+        // nothing new
+        assert_eq!(tap.read(), (&[1., 2., 3.][..], 4));
+
+        // only the latest is kept, and never more than max_bins
+        writer.publish(4, [4., 5., 6.].iter().copied());
+        writer.publish(8, (0..5).map(|x| x as f32));
+        assert_eq!(tap.read(), (&[0., 1., 2., 3.][..], 8));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::ring_buffer::split_range;
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// single-producer/single-consumer version of RingBuffer, for getting audio off the process thread
+// neither side allocates, locks or waits, they just move as much as there's room/data for
+
+struct Shared<T> {
+    data: Box<[UnsafeCell<T>]>,
+    // total elements written and read, wrapping
+    written: AtomicUsize,
+    read: AtomicUsize,
+}
+
+// each element is only accessed by one side at a time, which the counters enforce
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    // the two parts of len elements from the total count start
+    fn slices(&self, start: usize, len: usize) -> (&[UnsafeCell<T>], &[UnsafeCell<T>]) {
+        let (split, range) = split_range(self.size(), start % self.size(), len);
+        let (tail, head) = self.data.split_at(split);
+        (&head[range], tail)
+    }
+}
+
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub fn channel<T: Copy + Default>(size: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(size > 0);
+    let shared = Arc::new(Shared {
+        data: (0..size).map(|_| UnsafeCell::new(Default::default())).collect(),
+        written: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+    });
+    (Producer { shared: shared.clone() }, Consumer { shared })
+}
+
+impl<T: Copy> Producer<T> {
+    pub fn free(&self) -> usize {
+        let written = self.shared.written.load(Ordering::Relaxed);
+        let read = self.shared.read.load(Ordering::Acquire);
+        self.shared.size() - written.wrapping_sub(read)
+    }
+
+    // returns how many elements were written, which is less than src.len() if the consumer fell behind
+    pub fn push_slice(&mut self, src: &[T]) -> usize {
+        let len = src.len().min(self.free());
+        let written = self.shared.written.load(Ordering::Relaxed);
+
+        let (head, tail) = self.shared.slices(written, len);
+        for (y, x) in head.iter().chain(tail).zip(src) {
+            unsafe { *y.get() = *x; }
+        }
+
+        self.shared.written.store(written.wrapping_add(len), Ordering::Release);
+        len
+    }
+}
+
+impl<T: Copy> Consumer<T> {
+    pub fn len(&self) -> usize {
+        let written = self.shared.written.load(Ordering::Acquire);
+        let read = self.shared.read.load(Ordering::Relaxed);
+        written.wrapping_sub(read)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // returns how many elements were read
+    pub fn pop_slice(&mut self, dst: &mut [T]) -> usize {
+        let len = dst.len().min(self.len());
+        let read = self.shared.read.load(Ordering::Relaxed);
+
+        let (head, tail) = self.shared.slices(read, len);
+        for (y, x) in dst.iter_mut().zip(head.iter().chain(tail)) {
+            *y = unsafe { *x.get() };
+        }
+
+        self.shared.read.store(read.wrapping_add(len), Ordering::Release);
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spsc() {
+        let (mut tx, mut rx) = channel::<u32>(8);
+
+        assert_eq!(tx.push_slice(&[1, 2, 3, 4, 5]), 5);
+        let out = &mut [0; 3];
+        assert_eq!(rx.pop_slice(out), 3);
+        assert_eq!(out, &[1, 2, 3]);
+
+        // wraps around, and only fills what's free
+        assert_eq!(tx.push_slice(&[6, 7, 8, 9, 10, 11, 12]), 6);
+        assert_eq!(tx.free(), 0);
+        let out = &mut [0; 10];
+        assert_eq!(rx.pop_slice(out), 8);
+        assert_eq!(out[..8], [4, 5, 6, 7, 8, 9, 10, 11]);
+
+        let thread = std::thread::spawn(move || {
+            let mut next = 0;
+            while next < 10000 {
+                let chunk: Vec<_> = (next..(next + 100).min(10000)).collect();
+                next += tx.push_slice(&chunk) as u32;
+                std::thread::yield_now();
+            }
+        });
+        let mut received = Vec::new();
+        while received.len() < 10000 {
+            let out = &mut [0; 64];
+            let n = rx.pop_slice(out);
+            received.extend_from_slice(&out[..n]);
+            std::thread::yield_now();
+        }
+        thread.join().unwrap();
+        assert!(received.into_iter().eq(0..10000));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::spectral_decay::{FrameBuffers, SpectralDecay, SpectralDecayError, SpectralDecayParameters};
+use crate::grain_table::GrainTable;
+use crate::window::WindowKind;
+use std::sync::Arc;
+
+// frames deinterleaved at a time
+const BLOCK: usize = 512;
+
+// two channels of interleaved L/R frames, like cpal, WAV files and web audio hand out,
+// sharing one set of windows and processed one after another on the calling thread,
+// so they take turns with one set of frame buffers
+pub struct StereoSpectralDecay {
+    channels: [SpectralDecay; 2],
+    buffers: FrameBuffers,
+    // left in, right in, left out, right out
+    scratch: [[f32; BLOCK]; 4],
+}
+
+impl StereoSpectralDecay {
+    pub fn new(grain_sizes: &[usize]) -> Self {
+        Self::try_new(grain_sizes).unwrap()
+    }
+
+    pub fn try_new(grain_sizes: &[usize]) -> Result<Self, SpectralDecayError> {
+        let table = Arc::new(GrainTable::new(grain_sizes, WindowKind::Hann)?);
+        let mut channels = [SpectralDecay::try_with_table(table.clone())?, SpectralDecay::try_with_table(table)?];
+        let buffers = SpectralDecay::share_frame_buffers(&mut channels);
+        Ok(Self {
+            channels,
+            buffers,
+            scratch: [[0.; BLOCK]; 4],
+        })
+    }
+
+    // both channels, for anything not covered here (taps, meters, prints)
+    // one processed directly instead of through here allocates frame buffers of its own
+    pub fn channels(&mut self) -> &mut [SpectralDecay; 2] {
+        &mut self.channels
+    }
+
+    pub fn set_params(&mut self, params: SpectralDecayParameters) {
+        for sd in self.channels.iter_mut() {
+            sd.set_params(params);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for sd in self.channels.iter_mut() {
+            sd.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for sd in self.channels.iter_mut() {
+            sd.reset();
+        }
+    }
+
+    pub fn delay(&self) -> usize {
+        self.channels[0].delay()
+    }
+
+    // input and output are L, R, L, R, ...
+    pub fn process_interleaved(&mut self, input: &[f32], output: &mut [f32]) {
+        output.copy_from_slice(input);
+        self.process_interleaved_in_place(output);
+    }
+
+    // buf is L, R, L, R, ... and read and written in place
+    pub fn process_interleaved_in_place(&mut self, buf: &mut [f32]) {
+        assert_eq!(buf.len() % 2, 0);
+
+        let [left_in, right_in, left_out, right_out] = &mut self.scratch;
+        for frames in buf.chunks_mut(2 * BLOCK) {
+            let len = frames.len() / 2;
+            for (i, frame) in frames.chunks(2).enumerate() {
+                left_in[i] = frame[0];
+                right_in[i] = frame[1];
+            }
+
+            let buffers = &mut self.buffers;
+            self.channels[0].lend_frame_buffers(buffers, |sd| sd.process(&left_in[..len], &mut left_out[..len]));
+            self.channels[1].lend_frame_buffers(buffers, |sd| sd.process(&right_in[..len], &mut right_out[..len]));
+
+            for (i, frame) in frames.chunks_mut(2).enumerate() {
+                frame[0] = left_out[i];
+                frame[1] = right_out[i];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stereo_interleaved() {
+        let n = 32;
+        let mut p = SpectralDecayParameters::default();
+        p.loss = 0.5;
+        let left: Vec<_> = (0..40 * n).map(|x| (x as f32 * 0.1).sin()).collect();
+        let right: Vec<_> = (0..40 * n).map(|x| (x as f32 * 0.37).cos()).collect();
+
+        let mut expected = [vec![0.; left.len()], vec![0.; right.len()]];
+        for (c, input) in [&left, &right].iter().enumerate() {
+            let mut sd = SpectralDecay::new(&[n]);
+            sd.set_params(p);
+            sd.process(input, &mut expected[c]);
+        }
+
+        let interleaved: Vec<_> = left.iter().zip(&right).flat_map(|(&l, &r)| vec![l, r]).collect();
+        let mut stereo = StereoSpectralDecay::new(&[n]);
+        stereo.set_params(p);
+        let mut output = vec![0.; interleaved.len()];
+        stereo.process_interleaved(&interleaved, &mut output);
+        for (i, frame) in output.chunks(2).enumerate() {
+            assert_eq!(frame, &[expected[0][i], expected[1][i]]);
+        }
+
+        let mut stereo = StereoSpectralDecay::new(&[n]);
+        stereo.set_params(p);
+        let mut buf = interleaved;
+        stereo.process_interleaved_in_place(&mut buf);
+        assert_eq!(buf, output);
+    }
+}
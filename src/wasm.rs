@@ -0,0 +1,85 @@
+use crate::{fft_sizes, SpectralDecay, SpectralDecayParameters};
+use wasm_bindgen::prelude::*;
+
+// one channel of the effect for the browser, e.g. inside an AudioWorkletProcessor
+// build with: cargo build --lib --release --target wasm32-unknown-unknown --features wasm
+// then run wasm-bindgen on the output for the js glue
+#[wasm_bindgen]
+pub struct WasmSpectralDecay {
+    sd: SpectralDecay,
+    params: SpectralDecayParameters,
+}
+
+#[wasm_bindgen]
+impl WasmSpectralDecay {
+    #[wasm_bindgen(constructor)]
+    pub fn new(sample_rate: f32) -> Self {
+        let mut sd = SpectralDecay::new(&fft_sizes::default_sizes(sample_rate));
+        sd.set_sample_rate(sample_rate);
+        Self { sd, params: Default::default() }
+    }
+
+    // takes and returns a Float32Array of the same length
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let mut output = vec![0.; input.len()];
+        self.sd.process(input, &mut output);
+        output
+    }
+
+    // latency in samples
+    pub fn delay(&self) -> usize {
+        self.sd.delay()
+    }
+
+    // the new latency if a setter changed it since the last call, undefined otherwise
+    pub fn latency_changed(&mut self) -> Option<usize> {
+        self.sd.latency_changed()
+    }
+
+    pub fn reset(&mut self) {
+        self.sd.reset();
+    }
+
+    pub fn set_grain(&mut self, value: f32) {
+        self.update(|params| params.grain_select = value);
+    }
+
+    pub fn set_delay(&mut self, value: f32) {
+        self.update(|params| params.delay_select = value);
+    }
+
+    pub fn set_fuzz(&mut self, value: f32) {
+        self.update(|params| params.fuzz = value);
+    }
+
+    pub fn set_loss(&mut self, value: f32) {
+        self.update(|params| params.loss = value);
+    }
+
+    pub fn set_glitch_freq(&mut self, value: f32) {
+        self.update(|params| params.glitch_freq = value);
+    }
+
+    pub fn set_glitch_gain(&mut self, value: f32) {
+        self.update(|params| params.glitch_gain = value);
+    }
+
+    pub fn set_mix(&mut self, value: f32) {
+        self.update(|params| params.mix = value);
+    }
+
+    pub fn set_pitch(&mut self, value: f32) {
+        self.update(|params| params.pitch = value);
+    }
+
+    pub fn set_freeze(&mut self, value: bool) {
+        self.update(|params| params.freeze = value);
+    }
+}
+
+impl WasmSpectralDecay {
+    fn update(&mut self, f: impl FnOnce(&mut SpectralDecayParameters)) {
+        f(&mut self.params);
+        self.sd.set_params(self.params);
+    }
+}
@@ -0,0 +1,94 @@
+use std::f32::consts::PI;
+
+// tukey taper length as a fraction of the window
+const TUKEY_ALPHA: f32 = 0.5;
+
+const BLACKMAN_HARRIS: &[f32] = &[0.35875, 0.48829, 0.14128, 0.01168];
+const FLAT_TOP: &[f32] = &[0.21557895, 0.41663158, 0.27726316, 0.083578947, 0.006947368];
+
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub enum WindowKind {
+    #[default]
+    Hann,
+    BlackmanHarris,
+    Tukey,
+    FlatTop,
+}
+
+fn cosine_sum(coefs: &[f32], phase: f32) -> f32 {
+    coefs.iter().enumerate().map(|(k, &a)| {
+        let sign = if k % 2 == 0 { 1. } else { -1. };
+        sign * a * (k as f32 * phase).cos()
+    }).sum()
+}
+
+// mean of a cosine sum over a period, and of its square
+fn cosine_sum_mean(coefs: &[f32]) -> f32 {
+    coefs[0]
+}
+fn cosine_sum_mean_square(coefs: &[f32]) -> f32 {
+    coefs[0] * coefs[0] + coefs[1..].iter().map(|a| a * a / 2.).sum::<f32>()
+}
+
+impl WindowKind {
+    // periodic, so that it overlap-adds cleanly
+    pub fn generate(self, n: usize) -> Vec<f32> {
+        (0..n).map(|x| {
+            let phase = x as f32 * 2. * PI / n as f32;
+            match self {
+                WindowKind::Hann => 0.5 - 0.5 * phase.cos(),
+                WindowKind::BlackmanHarris => cosine_sum(BLACKMAN_HARRIS, phase),
+                WindowKind::FlatTop => cosine_sum(FLAT_TOP, phase),
+                WindowKind::Tukey => {
+                    // hann tapers on each end, flat in the middle
+                    let t = x as f32 / n as f32;
+                    let edge = t.min(1. - t);
+                    if edge < TUKEY_ALPHA / 2. {
+                        0.5 - 0.5 * (2. * PI * edge / TUKEY_ALPHA).cos()
+                    } else {
+                        1.
+                    }
+                }
+            }
+        }).collect()
+    }
+
+    // the sum of overlapping grains is this times grain_size / hop
+    pub fn mean(self) -> f32 {
+        match self {
+            WindowKind::Hann => 0.5,
+            WindowKind::BlackmanHarris => cosine_sum_mean(BLACKMAN_HARRIS),
+            WindowKind::FlatTop => cosine_sum_mean(FLAT_TOP),
+            WindowKind::Tukey => 1. - TUKEY_ALPHA / 2.,
+        }
+    }
+
+    // same, for the window applied twice (analysis and synthesis)
+    pub fn mean_square(self) -> f32 {
+        match self {
+            WindowKind::Hann => 0.375,
+            WindowKind::BlackmanHarris => cosine_sum_mean_square(BLACKMAN_HARRIS),
+            WindowKind::FlatTop => cosine_sum_mean_square(FLAT_TOP),
+            WindowKind::Tukey => 1. - TUKEY_ALPHA * 5. / 8.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_gains() {
+        let n = 64;
+        for &kind in &[WindowKind::Hann, WindowKind::BlackmanHarris, WindowKind::Tukey, WindowKind::FlatTop] {
+            let w = kind.generate(n);
+
+            let mean = w.iter().sum::<f32>() / n as f32;
+            let mean_square = w.iter().map(|x| x * x).sum::<f32>() / n as f32;
+
+            assert!((mean - kind.mean()).abs() < 1e-4, "{:?} {}", kind, mean);
+            assert!((mean_square - kind.mean_square()).abs() < 1e-4, "{:?} {}", kind, mean_square);
+        }
+    }
+}